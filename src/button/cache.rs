@@ -0,0 +1,342 @@
+//! Render cache for Stream Deck buttons.
+//!
+//! This module provides a bounded cache of parsed SVG trees and of
+//! rendered button images, so that unchanged keys are cheap to render
+//! again.
+
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    hash::Hash,
+    sync::{Arc, Mutex},
+};
+
+use image::{DynamicImage, Rgba};
+use resvg::tiny_skia::Color;
+use resvg::usvg::{self, Tree};
+
+use super::icon::Icon;
+use super::layout::Layout;
+use super::types::{Button, RenderConfig};
+
+/// The default number of entries kept by a render cache.
+pub const DEFAULT_CACHE_CAPACITY: usize = 256;
+
+/// A bounded cache of parsed SVG trees and rendered button images.
+///
+/// Both parts of the cache keep up to `capacity` entries and evict the
+/// least recently used entry when full. A cache is shared by all clones
+/// of the `RenderConfig` it is attached to, and is safe to use from
+/// several rendering threads at once.
+pub struct RenderCache {
+    /// Parsed SVG trees, keyed by SVG source.
+    trees: Mutex<Lru<Arc<str>, Arc<Tree>>>,
+    /// Rendered images, keyed by everything that affects rendering.
+    images: Mutex<Lru<ImageKey, DynamicImage>>,
+}
+
+impl RenderCache {
+    /// Create a new cache that keeps up to `capacity` trees and
+    /// `capacity` images.
+    pub fn new(capacity: usize) -> Self {
+        RenderCache {
+            trees: Mutex::new(Lru::new(capacity)),
+            images: Mutex::new(Lru::new(capacity)),
+        }
+    }
+
+    /// Get the number of cached SVG trees.
+    pub fn tree_count(&self) -> usize {
+        self.trees.lock().unwrap().len()
+    }
+
+    /// Get the number of cached button images.
+    pub fn image_count(&self) -> usize {
+        self.images.lock().unwrap().len()
+    }
+
+    /// Remove all cached trees and images.
+    pub fn clear(&self) {
+        self.trees.lock().unwrap().clear();
+        self.images.lock().unwrap().clear();
+    }
+
+    /// Get the parsed tree of an SVG, parsing it if it is not cached.
+    pub(crate) fn tree(&self, svg_data: &str) -> Result<Arc<Tree>, usvg::Error> {
+        if let Some(tree) = self.trees.lock().unwrap().get(svg_data) {
+            return Ok(tree.clone());
+        }
+        let tree = Arc::new(Tree::from_data(svg_data.as_bytes(), &usvg::Options::default())?);
+        self.trees
+            .lock()
+            .unwrap()
+            .insert(Arc::from(svg_data), tree.clone());
+        Ok(tree)
+    }
+
+    /// Get the cached image of a button.
+    pub(crate) fn image(&self, key: &ImageKey) -> Option<DynamicImage> {
+        self.images.lock().unwrap().get(key).cloned()
+    }
+
+    /// Cache the image of a button.
+    pub(crate) fn insert_image(&self, key: ImageKey, image: DynamicImage) {
+        self.images.lock().unwrap().insert(key, image);
+    }
+}
+
+impl Default for RenderCache {
+    fn default() -> Self {
+        RenderCache::new(DEFAULT_CACHE_CAPACITY)
+    }
+}
+
+/// Everything that affects the image of a rendered button.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(crate) struct ImageKey {
+    /// The kind of button.
+    kind: u8,
+    /// The SVG source of the icon.
+    svg_data: Option<Icon>,
+    /// The text label.
+    text: Option<String>,
+    /// The colours, as RGBA bytes.
+    colors: [[u8; 4]; 2],
+    /// The width and height of the image.
+    size: (u32, u32),
+    /// The identifier of the font chain.
+    font: u64,
+    /// The bits of the font scale.
+    font_scale: u32,
+    /// The maximum number of lines of the label.
+    max_lines: usize,
+    /// The bits of the minimum font scale.
+    min_font_scale: Option<u32>,
+    /// The layout of the label and the icon.
+    layout: Layout,
+}
+
+impl ImageKey {
+    /// Create the key of a button rendered with a config.
+    ///
+    /// Returns `None` for buttons that are not worth caching, such as
+    /// custom and raster images and scrolling labels.
+    pub(crate) fn new(button: &Button, config: &RenderConfig) -> Option<Self> {
+        let (kind, svg_data, text, colors) = match button {
+            Button::Icon {
+                svg_data,
+                background,
+                foreground,
+            } => (
+                0,
+                Some(svg_data.clone()),
+                None,
+                [color_bytes(*background), color_bytes(*foreground)],
+            ),
+            Button::IconWithText {
+                svg_data,
+                text,
+                background,
+                foreground,
+            } => (
+                1,
+                Some(svg_data.clone()),
+                Some(text.clone()),
+                [color_bytes(*background), color_bytes(*foreground)],
+            ),
+            Button::Text {
+                text,
+                background,
+                foreground,
+            } => (
+                2,
+                None,
+                Some(text.clone()),
+                [color_bytes(*background), color_bytes(*foreground)],
+            ),
+            Button::Gradient {
+                start_color,
+                end_color,
+            } => (3, None, None, [rgba_bytes(*start_color), rgba_bytes(*end_color)]),
+            Button::CustomImage { .. } | Button::Image { .. } | Button::Marquee { .. } => {
+                return None
+            }
+        };
+        Some(ImageKey {
+            kind,
+            svg_data,
+            text,
+            colors,
+            size: (config.width, config.height),
+            font: config.font.id(),
+            font_scale: config.font_scale.to_bits(),
+            max_lines: config.max_lines,
+            min_font_scale: config.min_font_scale.map(f32::to_bits),
+            layout: config.layout,
+        })
+    }
+}
+
+fn color_bytes(color: Color) -> [u8; 4] {
+    let color = color.to_color_u8();
+    [color.red(), color.green(), color.blue(), color.alpha()]
+}
+
+fn rgba_bytes(color: Rgba<u8>) -> [u8; 4] {
+    color.0
+}
+
+/// A map that keeps up to a fixed number of entries and evicts the least
+/// recently used one when full.
+struct Lru<K, V> {
+    /// The maximum number of entries.
+    capacity: usize,
+    /// The entries and the tick of their last use.
+    entries: HashMap<K, (V, u64)>,
+    /// The tick of the last use of any entry.
+    tick: u64,
+}
+
+impl<K: Hash + Eq + Clone, V> Lru<K, V> {
+    fn new(capacity: usize) -> Self {
+        Lru {
+            capacity,
+            entries: HashMap::new(),
+            tick: 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.tick += 1;
+        let tick = self.tick;
+        self.entries.get_mut(key).map(|(value, used)| {
+            *used = tick;
+            &*value
+        })
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&key) {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.tick += 1;
+        self.entries.insert(key, (value, self.tick));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::button::render_button;
+
+    const SVG: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24"><rect width="24" height="24"/></svg>"#;
+
+    fn text(text: &str) -> Button {
+        Button::Text {
+            text: text.to_string(),
+            background: Color::BLACK,
+            foreground: Color::WHITE,
+        }
+    }
+
+    fn icon_with_text(text: &str) -> Button {
+        Button::IconWithText {
+            svg_data: Icon::from(SVG),
+            text: text.to_string(),
+            background: Color::BLACK,
+            foreground: Color::WHITE,
+        }
+    }
+
+    fn cached_config(capacity: usize) -> (RenderConfig, Arc<RenderCache>) {
+        let cache = Arc::new(RenderCache::new(capacity));
+        (RenderConfig::default().with_cache(cache.clone()), cache)
+    }
+
+    #[test]
+    fn repeated_buttons_are_taken_from_the_cache() {
+        let (config, cache) = cached_config(8);
+        let key = ImageKey::new(&text("A"), &config).unwrap();
+        let sentinel = DynamicImage::new_rgba8(1, 1);
+        cache.insert_image(key.clone(), sentinel.clone());
+
+        assert!(render_button(&text("A"), &config).unwrap() == sentinel);
+        assert!(ImageKey::new(&text("A"), &config) == Some(key.clone()));
+        assert!(ImageKey::new(&text("B"), &config) != Some(key.clone()));
+        let smaller = RenderConfig {
+            width: 64,
+            height: 64,
+            ..config.clone()
+        };
+        assert!(ImageKey::new(&text("A"), &smaller) != Some(key));
+    }
+
+    #[test]
+    fn icons_are_parsed_once() {
+        let (config, cache) = cached_config(8);
+        let first = render_button(&icon_with_text("A"), &config).unwrap();
+        render_button(&icon_with_text("B"), &config).unwrap();
+        assert_eq!(cache.tree_count(), 1);
+        assert_eq!(cache.image_count(), 2);
+
+        assert!(render_button(&icon_with_text("A"), &config).unwrap() == first);
+        assert_eq!(cache.image_count(), 2);
+        cache.clear();
+        assert_eq!((cache.tree_count(), cache.image_count()), (0, 0));
+    }
+
+    #[test]
+    fn least_recently_used_image_is_evicted() {
+        let (config, cache) = cached_config(2);
+        for label in ["A", "B", "A", "C"] {
+            render_button(&text(label), &config).unwrap();
+        }
+        assert_eq!(cache.image_count(), 2);
+        let cached = |label| cache.image(&ImageKey::new(&text(label), &config).unwrap());
+        assert!(cached("A").is_some());
+        assert!(cached("B").is_none());
+        assert!(cached("C").is_some());
+    }
+
+    #[test]
+    fn images_and_marquees_are_not_cached() {
+        let (config, cache) = cached_config(8);
+        let marquee = Button::Marquee {
+            svg_data: None,
+            text: "A long scrolling label".to_string(),
+            offset: 0,
+            background: Color::BLACK,
+            foreground: Color::WHITE,
+        };
+        let image = Button::CustomImage {
+            image: DynamicImage::new_rgba8(72, 72),
+        };
+        for button in [marquee, image] {
+            assert!(ImageKey::new(&button, &config).is_none());
+            render_button(&button, &config).unwrap();
+        }
+        assert_eq!(cache.image_count(), 0);
+    }
+}
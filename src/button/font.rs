@@ -0,0 +1,416 @@
+//! Fonts for Stream Deck button labels.
+//!
+//! This module provides font chains, which draw each character with the
+//! first font that has a glyph for it, so that labels can mix scripts,
+//! symbols and colour emoji.
+
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use ab_glyph::{
+    point, Font, FontArc, GlyphId, GlyphImageFormat, OutlinedGlyph, PxScale, ScaleFont,
+};
+use image::{imageops, DynamicImage, GenericImage, GenericImageView, Rgba};
+use imageproc::pixelops::weighted_sum;
+
+#[cfg(feature = "shaping")]
+use super::shaping::shape_line;
+
+/// The source of unique font chain identifiers.
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// The weight of a font.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FontWeight {
+    /// A light font.
+    Light,
+    /// A regular font.
+    Regular,
+    /// A medium font.
+    Medium,
+    /// A bold font.
+    Bold,
+}
+
+/// An ordered list of fonts used to draw labels.
+///
+/// Each character is drawn with the first font of the chain that has a
+/// glyph for it, or with the first font if none has. Fonts are parsed once
+/// when they are added, and the chain is cheap to clone.
+#[derive(Clone)]
+pub struct FontChain {
+    /// The fonts and their identifier, shared by all clones of the chain.
+    inner: Arc<FontChainInner>,
+}
+
+/// The fonts of a font chain.
+struct FontChainInner {
+    /// The fonts, from the preferred one to the last fallback.
+    fonts: Vec<FontArc>,
+    /// A unique identifier of this set of fonts.
+    id: u64,
+}
+
+/// A glyph laid out on a line of text.
+enum LaidOutGlyph<'a> {
+    /// A glyph drawn from its outline.
+    Outline(OutlinedGlyph),
+    /// A glyph drawn from a colour image.
+    Image(RasterGlyph<'a>),
+}
+
+/// A colour image of a glyph, placed on a line of text.
+struct RasterGlyph<'a> {
+    /// The PNG data of the image.
+    data: &'a [u8],
+    /// The left edge of the image, relative to the start of the line.
+    x: i32,
+    /// The top edge of the image, relative to the top of the line.
+    y: i32,
+    /// The width of the image once scaled to the font size.
+    width: u32,
+    /// The height of the image once scaled to the font size.
+    height: u32,
+}
+
+impl FontChain {
+    /// Create a new font chain with a single font
+    pub fn new(font: FontArc) -> Self {
+        FontChain::from_fonts(vec![font])
+    }
+
+    /// Create a new font chain from font data embedded in the binary
+    pub fn from_static(font_data: &'static [u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(FontChain::new(FontArc::try_from_slice(font_data)?))
+    }
+
+    /// Create a new font chain from a font file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(FontChain::new(load_font(path)?))
+    }
+
+    /// Add a font used for characters the previous fonts lack
+    pub fn with_fallback(self, font: FontArc) -> Self {
+        let mut fonts = self.inner.fonts.clone();
+        fonts.push(font);
+        FontChain::from_fonts(fonts)
+    }
+
+    /// Add a font file used for characters the previous fonts lack
+    pub fn with_fallback_file(
+        self,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(self.with_fallback(load_font(path)?))
+    }
+
+    /// Get the fonts of the chain, from the preferred one to the last
+    /// fallback
+    pub fn fonts(&self) -> &[FontArc] {
+        &self.inner.fonts
+    }
+
+    /// Create a font chain with a new identifier.
+    pub(crate) fn from_fonts(fonts: Vec<FontArc>) -> Self {
+        FontChain {
+            inner: Arc::new(FontChainInner {
+                fonts,
+                id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            }),
+        }
+    }
+
+    /// Get the identifier of the chain, which changes whenever its fonts do.
+    pub(crate) fn id(&self) -> u64 {
+        self.inner.id
+    }
+
+    /// Check whether the chain has a font to draw with.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.inner.fonts.is_empty()
+    }
+
+    /// Get the distance between two lines of text.
+    pub(crate) fn line_height(&self, scale: PxScale) -> f32 {
+        self.inner.fonts
+            .first()
+            .map(|font| font.as_scaled(scale).height())
+            .unwrap_or(0.0)
+    }
+
+    /// Get the width and height of a line of text.
+    pub(crate) fn text_size(&self, scale: PxScale, text: &str) -> (u32, u32) {
+        self.layout(scale, text, |_| {})
+    }
+
+    /// Draw a line of text whose top left corner is at `x` and `y`.
+    pub(crate) fn draw_text(
+        &self,
+        image: &mut DynamicImage,
+        color: Rgba<u8>,
+        x: i32,
+        y: i32,
+        scale: PxScale,
+        text: &str,
+    ) {
+        let (width, height) = (image.width() as i32, image.height() as i32);
+        self.layout(scale, text, |glyph| match glyph {
+            LaidOutGlyph::Outline(glyph) => {
+                let bounds = glyph.px_bounds();
+                glyph.draw(|gx, gy, coverage| {
+                    let image_x = gx as i32 + x + bounds.min.x.round() as i32;
+                    let image_y = gy as i32 + y + bounds.min.y.round() as i32;
+                    if (0..width).contains(&image_x) && (0..height).contains(&image_y) {
+                        let coverage = coverage.clamp(0.0, 1.0);
+                        let pixel = image.get_pixel(image_x as u32, image_y as u32);
+                        let blended = weighted_sum(pixel, color, 1.0 - coverage, coverage);
+                        image.put_pixel(image_x as u32, image_y as u32, blended);
+                    }
+                });
+            }
+            LaidOutGlyph::Image(glyph) => {
+                let Ok(glyph_image) = image::load_from_memory(glyph.data) else {
+                    return;
+                };
+                let glyph_image = glyph_image.resize_exact(
+                    glyph.width,
+                    glyph.height,
+                    imageops::FilterType::Triangle,
+                );
+                imageops::overlay(
+                    image,
+                    &glyph_image,
+                    (x + glyph.x) as i64,
+                    (y + glyph.y) as i64,
+                );
+            }
+        });
+    }
+
+    /// Get the index of the font that draws a character.
+    pub(crate) fn font_index(&self, c: char) -> usize {
+        (0..self.inner.fonts.len())
+            .find(|index| self.has_glyph(*index, c))
+            .unwrap_or(0)
+    }
+
+    /// Check whether a font of the chain has a glyph for a character.
+    pub(crate) fn has_glyph(&self, index: usize, c: char) -> bool {
+        self.inner.fonts[index].glyph_id(c).0 != 0
+    }
+
+    /// Lay out a line of text, calling `f` for each visible glyph.
+    ///
+    /// Glyphs are placed relative to the top left corner of the line, on
+    /// the baseline of the first font. Returns the width and height of the
+    /// line.
+    fn layout<'a>(
+        &'a self,
+        scale: PxScale,
+        text: &str,
+        f: impl FnMut(LaidOutGlyph<'a>),
+    ) -> (u32, u32) {
+        #[cfg(feature = "shaping")]
+        {
+            self.layout_shaped(scale, text, f)
+        }
+        #[cfg(not(feature = "shaping"))]
+        {
+            self.layout_unshaped(scale, text, f)
+        }
+    }
+
+    /// Lay out a line of text with the shaper.
+    #[cfg(feature = "shaping")]
+    fn layout_shaped<'a>(
+        &'a self,
+        scale: PxScale,
+        text: &str,
+        mut f: impl FnMut(LaidOutGlyph<'a>),
+    ) -> (u32, u32) {
+        let Some(primary) = self.inner.fonts.first() else {
+            return (0, 0);
+        };
+        let baseline = primary.as_scaled(scale).ascent();
+        let (glyphs, width) = shape_line(self, scale, text);
+        let mut h = 0f32;
+
+        for glyph in glyphs {
+            let font = &self.inner.fonts[glyph.font];
+            let positioned = glyph
+                .id
+                .with_scale_and_position(scale, point(glyph.x, baseline - glyph.y));
+            if let Some(outlined) = font.outline_glyph(positioned) {
+                h = h.max(outlined.px_bounds().height());
+                f(LaidOutGlyph::Outline(outlined));
+            } else if let Some(raster) = raster_glyph(font, glyph.id, scale, glyph.x, baseline) {
+                h = h.max(raster.height as f32);
+                f(LaidOutGlyph::Image(raster));
+            }
+        }
+
+        (width as u32, h as u32)
+    }
+
+    /// Lay out a line of text one character at a time, with pairwise
+    /// kerning only.
+    #[cfg_attr(feature = "shaping", allow(dead_code))]
+    fn layout_unshaped<'a>(
+        &'a self,
+        scale: PxScale,
+        text: &str,
+        mut f: impl FnMut(LaidOutGlyph<'a>),
+    ) -> (u32, u32) {
+        let Some(primary) = self.inner.fonts.first() else {
+            return (0, 0);
+        };
+        let baseline = primary.as_scaled(scale).ascent();
+        let (mut w, mut h) = (0f32, 0f32);
+        let mut last: Option<(usize, GlyphId)> = None;
+
+        for c in text.chars() {
+            let index = self.font_index(c);
+            let font = &self.inner.fonts[index];
+            let scaled = font.as_scaled(scale);
+            let glyph_id = scaled.glyph_id(c);
+            let glyph = glyph_id.with_scale_and_position(scale, point(w, baseline));
+            let x = w;
+            w += scaled.h_advance(glyph_id);
+            if let Some(outlined) = scaled.outline_glyph(glyph) {
+                if let Some((last_index, last_id)) = last {
+                    if last_index == index {
+                        w += scaled.kern(glyph_id, last_id);
+                    }
+                }
+                last = Some((index, glyph_id));
+                h = h.max(outlined.px_bounds().height());
+                f(LaidOutGlyph::Outline(outlined));
+            } else if let Some(raster) = raster_glyph(font, glyph_id, scale, x, baseline) {
+                last = None;
+                h = h.max(raster.height as f32);
+                f(LaidOutGlyph::Image(raster));
+            }
+        }
+
+        (w as u32, h as u32)
+    }
+}
+
+impl PartialEq for FontChain {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner.id == other.inner.id
+    }
+}
+
+impl Default for FontChain {
+    fn default() -> Self {
+        FontChain::from_static(include_bytes!("../../fonts/Roboto-Medium.ttf"))
+            .expect("Bundled font is valid")
+    }
+}
+
+/// Load a font from a file.
+fn load_font(path: impl AsRef<Path>) -> Result<FontArc, Box<dyn std::error::Error>> {
+    Ok(FontArc::try_from_vec(std::fs::read(path)?)?)
+}
+
+/// Get the colour image of a glyph, placed on a line of text at the font
+/// size.
+fn raster_glyph(
+    font: &FontArc,
+    glyph_id: GlyphId,
+    scale: PxScale,
+    x: f32,
+    baseline: f32,
+) -> Option<RasterGlyph<'_>> {
+    let pixels_per_em = font.as_scaled(scale).scale_factor().vertical * font.units_per_em()?;
+    let raster = font.glyph_raster_image2(glyph_id, pixels_per_em.ceil() as u16)?;
+    if !matches!(raster.format, GlyphImageFormat::Png) {
+        return None;
+    }
+    let factor = pixels_per_em / raster.pixels_per_em as f32;
+    let width = (raster.width as f32 * factor).round().max(1.0);
+    let height = (raster.height as f32 * factor).round().max(1.0);
+    let left = x + raster.origin.x * factor;
+    let bottom = baseline - raster.origin.y * factor;
+    Some(RasterGlyph {
+        data: raster.data,
+        x: left.round() as i32,
+        y: (bottom - height).round() as i32,
+        width: width as u32,
+        height: height as u32,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{button::RenderConfig, view::Button};
+
+    const ROBOTO: &[u8] = include_bytes!("../../fonts/Roboto-Medium.ttf");
+
+    #[test]
+    fn chains_change_identity_with_their_fonts() {
+        let chain = FontChain::from_static(ROBOTO).unwrap();
+        assert_eq!(chain.fonts().len(), 1);
+        assert!(chain.clone() == chain);
+
+        let font = chain.fonts()[0].clone();
+        let with_fallback = chain.clone().with_fallback(font);
+        assert_eq!(with_fallback.fonts().len(), 2);
+        assert_eq!(chain.fonts().len(), 1);
+        assert!(with_fallback != chain);
+        assert_ne!(with_fallback.id(), chain.id());
+        assert!(FontChain::from_static(ROBOTO).unwrap() != chain);
+    }
+
+    #[test]
+    fn invalid_fonts_are_rejected() {
+        assert!(FontChain::from_static(b"not a font").is_err());
+        assert!(FontChain::from_file("fonts/missing.ttf").is_err());
+        assert!(FontChain::from_file("fonts/Roboto-Medium.ttf").is_ok());
+        assert!(FontChain::default()
+            .with_fallback_file("Cargo.toml")
+            .is_err());
+    }
+
+    #[test]
+    fn missing_glyphs_fall_back_to_the_first_font() {
+        let chain = FontChain::default();
+        let chain = chain.clone().with_fallback(chain.fonts()[0].clone());
+        assert!(chain.has_glyph(0, 'ą'));
+        assert!(!chain.has_glyph(1, '日'));
+        assert_eq!(chain.font_index('ą'), 0);
+        assert_eq!(chain.font_index('日'), 0);
+
+        let empty = FontChain::from_fonts(Vec::new());
+        assert!(empty.is_empty());
+        assert_eq!(empty.text_size(PxScale::from(14.0), "Label"), (0, 0));
+        assert!(chain.text_size(PxScale::from(14.0), "Label").0 > 0);
+    }
+
+    #[test]
+    fn buttons_pick_their_font() {
+        let bold = FontChain::from_static(ROBOTO).unwrap();
+        let own = FontChain::from_static(ROBOTO).unwrap();
+        let config = RenderConfig::default()
+            .with_weight_font(FontWeight::Light, FontChain::default())
+            .with_weight_font(FontWeight::Bold, FontChain::default())
+            .with_weight_font(FontWeight::Bold, bold.clone());
+        assert_eq!(config.weight_fonts.len(), 2);
+
+        let button = Button::text("Label".to_string());
+        assert!(button.render_config(&config).font == config.font);
+        let button = button.with_font_weight(FontWeight::Bold);
+        assert!(button.render_config(&config).font == bold);
+        let button = button.with_font(own.clone());
+        assert!(button.render_config(&config).font == own);
+        let button = Button::text("Label".to_string()).with_font_weight(FontWeight::Medium);
+        assert!(button.render_config(&config).font == config.font);
+    }
+}
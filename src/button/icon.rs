@@ -0,0 +1,152 @@
+//! Icons for Stream Deck buttons.
+//!
+//! This module provides the SVG icon type used by buttons, which can hold
+//! an icon compiled into the binary, such as the `md_icons` constants, or
+//! one loaded or generated at runtime.
+
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    path::Path,
+    sync::Arc,
+};
+
+use resvg::usvg::{self, Tree};
+
+/// The SVG source of an icon.
+///
+/// Icons are cheap to clone: static icons are borrowed, and icons created
+/// at runtime are shared.
+///
+/// Only `from_svg`, `from_bytes` and `from_file` check that the source is a
+/// valid SVG. The `From` conversions accept any string, and an invalid one
+/// makes rendering the button fail instead.
+#[derive(Clone)]
+pub struct Icon {
+    /// The SVG source.
+    data: IconData,
+}
+
+/// The storage of the SVG source of an icon.
+#[derive(Clone)]
+enum IconData {
+    /// SVG source compiled into the binary.
+    Static(&'static str),
+    /// SVG source created at runtime.
+    Shared(Arc<str>),
+}
+
+impl Icon {
+    /// Create an icon from SVG source compiled into the binary
+    pub const fn from_static(svg_data: &'static str) -> Self {
+        Icon {
+            data: IconData::Static(svg_data),
+        }
+    }
+
+    /// Create an icon from SVG source
+    ///
+    /// This method fails if the source is not a valid SVG.
+    pub fn from_svg(svg_data: impl Into<Arc<str>>) -> Result<Self, Box<dyn std::error::Error>> {
+        let svg_data = svg_data.into();
+        Tree::from_str(&svg_data, &usvg::Options::default())?;
+        Ok(Icon {
+            data: IconData::Shared(svg_data),
+        })
+    }
+
+    /// Create an icon from SVG source given as bytes
+    ///
+    /// This method fails if the bytes are not valid UTF-8 or not a valid SVG.
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>) -> Result<Self, Box<dyn std::error::Error>> {
+        Icon::from_svg(String::from_utf8(bytes.into())?)
+    }
+
+    /// Load an icon from an SVG file
+    ///
+    /// This method fails if the file cannot be read or is not a valid SVG.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        Icon::from_svg(std::fs::read_to_string(path)?)
+    }
+
+    /// Get the SVG source of the icon
+    pub fn as_str(&self) -> &str {
+        match &self.data {
+            IconData::Static(svg_data) => svg_data,
+            IconData::Shared(svg_data) => svg_data,
+        }
+    }
+}
+
+impl Deref for Icon {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<&'static str> for Icon {
+    fn from(svg_data: &'static str) -> Self {
+        Icon::from_static(svg_data)
+    }
+}
+
+/// Create an icon from SVG source without checking it
+///
+/// Use `Icon::from_svg` to reject invalid SVG when the icon is created.
+impl From<String> for Icon {
+    fn from(svg_data: String) -> Self {
+        Icon::from(Arc::<str>::from(svg_data))
+    }
+}
+
+/// Create an icon from shared SVG source without checking it
+///
+/// Use `Icon::from_svg` to reject invalid SVG when the icon is created.
+impl From<Arc<str>> for Icon {
+    fn from(svg_data: Arc<str>) -> Self {
+        Icon {
+            data: IconData::Shared(svg_data),
+        }
+    }
+}
+
+impl PartialEq for Icon {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.as_str(), other.as_str()) || self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Icon {}
+
+impl Hash for Icon {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+impl fmt::Debug for Icon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Icon").field(&self.as_str().len()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SVG: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24"><rect width="24" height="24"/></svg>"#;
+
+    #[test]
+    fn only_constructors_validate() {
+        assert!(Icon::from_svg(SVG).is_ok());
+        assert!(Icon::from_bytes(SVG.as_bytes()).is_ok());
+        assert!(Icon::from_svg("not an svg").is_err());
+        assert!(Icon::from_bytes(vec![0xff, 0xfe]).is_err());
+
+        let unchecked = Icon::from(String::from("not an svg"));
+        assert_eq!(unchecked.as_str(), "not an svg");
+    }
+}
@@ -0,0 +1,137 @@
+//! Layout of labels and icons on Stream Deck buttons.
+//!
+//! This module provides the settings that place the label and the icon of
+//! a button on its key.
+
+use std::hash::{Hash, Hasher};
+
+/// The vertical position of a label on a key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TextPosition {
+    /// The label is drawn at the top of the key.
+    Top,
+    /// The label is centred vertically.
+    Center,
+    /// The label is drawn at the bottom of the key.
+    Bottom,
+}
+
+/// Settings for placing the label and the icon of a button.
+///
+/// By default, labels of text-only keys are centred, labels under an icon
+/// are drawn at the bottom, and icons take 60% of the shorter side of the
+/// key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Layout {
+    /// The position of the label, or `None` to pick it from the content.
+    pub(crate) text_position: Option<TextPosition>,
+    /// Whether only the icon is drawn on keys that have one.
+    pub(crate) icon_only: bool,
+    /// The size of the icon as a fraction of the shorter side of the key.
+    pub(crate) icon_size: f32,
+    /// The offset of the icon from its default position, in pixels.
+    pub(crate) icon_offset: (i32, i32),
+    /// The space kept free between the label and the key edges, in pixels,
+    /// horizontally and vertically.
+    pub(crate) padding: (u32, u32),
+}
+
+impl Layout {
+    /// Create a new layout with the default settings
+    pub fn new() -> Self {
+        Layout {
+            text_position: None,
+            icon_only: false,
+            icon_size: 0.6,
+            icon_offset: (0, 0),
+            padding: (4, 6),
+        }
+    }
+
+    /// Set the position of the label
+    pub fn with_text_position(self, text_position: TextPosition) -> Self {
+        Layout {
+            text_position: Some(text_position),
+            ..self
+        }
+    }
+
+    /// Draw only the icon on keys that have one
+    pub fn icon_only(self) -> Self {
+        Layout {
+            icon_only: true,
+            ..self
+        }
+    }
+
+    /// Set the size of the icon as a fraction of the shorter side of the key
+    pub fn with_icon_size(self, icon_size: f32) -> Self {
+        Layout { icon_size, ..self }
+    }
+
+    /// Move the icon from its default position by the given number of pixels
+    pub fn with_icon_offset(self, x: i32, y: i32) -> Self {
+        Layout {
+            icon_offset: (x, y),
+            ..self
+        }
+    }
+
+    /// Set the space kept free between the label and the key edges
+    pub fn with_padding(self, horizontal: u32, vertical: u32) -> Self {
+        Layout {
+            padding: (horizontal, vertical),
+            ..self
+        }
+    }
+
+    /// Get the side of the square box an icon is fitted into on a key of
+    /// the given size.
+    pub(crate) fn icon_box(&self, key_width: u32, key_height: u32) -> f32 {
+        key_width.min(key_height) as f32 * self.icon_size
+    }
+
+    /// Get the top of the icon box on a key of the given height.
+    pub(crate) fn icon_top(&self, key_height: u32) -> f32 {
+        key_height as f32 * 0.15 + self.icon_offset.1 as f32
+    }
+
+    /// Get the position of a label, depending on whether the key has an
+    /// icon.
+    pub(crate) fn text_position(&self, has_icon: bool) -> TextPosition {
+        match (self.text_position, has_icon) {
+            (Some(text_position), _) => text_position,
+            (None, true) => TextPosition::Bottom,
+            (None, false) => TextPosition::Center,
+        }
+    }
+
+    /// Get the top of a label of the given height on a key of the given
+    /// height.
+    pub(crate) fn text_top(&self, has_icon: bool, key_height: i32, text_height: i32) -> i32 {
+        let padding = self.padding.1 as i32;
+        match self.text_position(has_icon) {
+            TextPosition::Top => padding,
+            TextPosition::Center => (key_height - text_height) / 2,
+            TextPosition::Bottom => key_height - text_height - padding,
+        }
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout::new()
+    }
+}
+
+impl Eq for Layout {}
+
+impl Hash for Layout {
+    fn hash<S: Hasher>(&self, state: &mut S) {
+        self.text_position.hash(state);
+        self.icon_only.hash(state);
+        self.icon_size.to_bits().hash(state);
+        self.icon_offset.hash(state);
+        self.padding.hash(state);
+    }
+}
@@ -3,9 +3,25 @@
 //! This module provides types and functions for creating and rendering
 //! buttons on the Stream Deck.
 
+mod cache;
+mod font;
+mod icon;
+mod layout;
+mod raster;
 mod render;
+#[cfg(feature = "shaping")]
+mod shaping;
+mod text;
 mod types;
 
 // Re-export public items
+pub use ab_glyph::FontArc;
+pub use self::cache::{RenderCache, DEFAULT_CACHE_CAPACITY};
+pub use self::font::{FontChain, FontWeight};
+pub use self::icon::Icon;
+pub use self::layout::{Layout, TextPosition};
+pub use self::raster::{ImageFit, RasterImage};
 pub use self::render::{render_button, set_button};
-pub use self::types::{Button, RenderConfig};
\ No newline at end of file
+pub use self::types::{Button, RenderConfig, DEFAULT_MAX_LINES};
+
+pub(crate) use self::text::marquee_cycle;
\ No newline at end of file
@@ -0,0 +1,138 @@
+//! Raster images for Stream Deck buttons.
+//!
+//! This module provides images such as avatars, album art or screenshots,
+//! which are scaled to the key with a fit or fill mode.
+
+use std::{fmt, path::Path, sync::Arc};
+
+use image::DynamicImage;
+
+/// How a raster image is scaled to a key.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ImageFit {
+    /// The whole image is shown, centred on the key background.
+    #[default]
+    Fit,
+    /// The image covers the whole key, and is cropped around its centre.
+    Fill,
+}
+
+/// A raster image shown on a button.
+///
+/// Images are decoded once and cheap to clone.
+#[derive(Clone)]
+pub struct RasterImage {
+    /// The image and its fit mode, shared by all clones.
+    inner: Arc<RasterImageInner>,
+}
+
+/// The image and the fit mode of a raster image.
+struct RasterImageInner {
+    /// The decoded image, shared with copies that only differ in fit mode.
+    image: Arc<DynamicImage>,
+    /// How the image is scaled to the key.
+    fit: ImageFit,
+}
+
+impl RasterImage {
+    /// Create a new raster image that fits the key
+    pub fn new(image: impl Into<Arc<DynamicImage>>) -> Self {
+        RasterImage::from_parts(image.into(), ImageFit::Fit)
+    }
+
+    /// Decode a PNG, JPEG or WebP image
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(RasterImage::new(image::load_from_memory(bytes)?))
+    }
+
+    /// Load a PNG, JPEG or WebP image from a file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(RasterImage::new(image::open(path)?))
+    }
+
+    /// Set how the image is scaled to the key
+    pub fn with_fit(self, fit: ImageFit) -> Self {
+        RasterImage::from_parts(self.inner.image.clone(), fit)
+    }
+
+    /// Get the decoded image
+    pub fn image(&self) -> &DynamicImage {
+        &self.inner.image
+    }
+
+    /// Get how the image is scaled to the key
+    pub fn fit(&self) -> ImageFit {
+        self.inner.fit
+    }
+
+    /// Create a raster image from a shared image and a fit mode.
+    fn from_parts(image: Arc<DynamicImage>, fit: ImageFit) -> Self {
+        RasterImage {
+            inner: Arc::new(RasterImageInner { image, fit }),
+        }
+    }
+}
+
+impl From<DynamicImage> for RasterImage {
+    fn from(image: DynamicImage) -> Self {
+        RasterImage::new(image)
+    }
+}
+
+impl From<Arc<DynamicImage>> for RasterImage {
+    fn from(image: Arc<DynamicImage>) -> Self {
+        RasterImage::new(image)
+    }
+}
+
+impl PartialEq for RasterImage {
+    fn eq(&self, other: &Self) -> bool {
+        self.fit() == other.fit()
+            && (Arc::ptr_eq(&self.inner.image, &other.inner.image)
+                || self.inner.image == other.inner.image)
+    }
+}
+
+impl fmt::Debug for RasterImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RasterImage")
+            .field("width", &self.image().width())
+            .field("height", &self.image().height())
+            .field("fit", &self.fit())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, Rgba, RgbaImage};
+    use std::io::Cursor;
+
+    fn red(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, Rgba([255, 0, 0, 255])))
+    }
+
+    #[test]
+    fn images_are_decoded_from_bytes() {
+        let mut png = Vec::new();
+        red(4, 2)
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        let image = RasterImage::from_bytes(&png).unwrap();
+        assert_eq!((image.image().width(), image.image().height()), (4, 2));
+        assert_eq!(image.fit(), ImageFit::Fit);
+        assert!(image == RasterImage::new(red(4, 2)));
+        assert!(RasterImage::from_bytes(b"not an image").is_err());
+    }
+
+    #[test]
+    fn fit_mode_shares_the_image() {
+        let image = RasterImage::new(red(4, 2));
+        let filled = image.clone().with_fit(ImageFit::Fill);
+        assert_eq!(filled.fit(), ImageFit::Fill);
+        assert!(Arc::ptr_eq(&image.inner.image, &filled.inner.image));
+        assert!(filled != image);
+        assert!(filled.with_fit(ImageFit::Fit) == image);
+    }
+}
@@ -1,32 +1,63 @@
-use ab_glyph::{FontRef, PxScale};
 use elgato_streamdeck::{AsyncStreamDeck, StreamDeckError};
 use image::GenericImage;
-use image::{DynamicImage, Rgba};
-use imageproc::drawing::{draw_text_mut, text_size};
+use image::{imageops, DynamicImage, Rgba, RgbaImage};
+use imageproc::pixelops::weighted_sum;
 use resvg::tiny_skia::{Color, Pixmap, PremultipliedColorU8, Transform};
 use resvg::usvg::{self, Tree};
 use std::error::Error;
+use std::sync::Arc;
 
+use super::cache::ImageKey;
+use super::font::FontChain;
+use super::raster::{ImageFit, RasterImage};
+use super::text::{draw_marquee, TextBlock};
 use super::types::{Button, RenderConfig};
 
 /// Renders a button to a DynamicImage
+///
+/// Images are taken from the cache of the config when possible.
 pub fn render_button(
     button: &Button,
     config: &RenderConfig,
+) -> Result<DynamicImage, Box<dyn std::error::Error>> {
+    let Some(cache) = &config.cache else {
+        return render_uncached(button, config);
+    };
+    let Some(key) = ImageKey::new(button, config) else {
+        return render_uncached(button, config);
+    };
+    if let Some(image) = cache.image(&key) {
+        return Ok(image);
+    }
+    let image = render_uncached(button, config)?;
+    cache.insert_image(key, image.clone());
+    Ok(image)
+}
+
+fn render_uncached(
+    button: &Button,
+    config: &RenderConfig,
 ) -> Result<DynamicImage, Box<dyn std::error::Error>> {
     match button {
         Button::Icon {
             svg_data,
             background,
             foreground,
-        } => render_svg(svg_data.as_bytes(), config, *background, *foreground),
+        } => render_svg(svg_data, config, *background, *foreground),
         Button::IconWithText {
             svg_data,
             text,
             foreground,
             background,
-        } => render_svg_with_text(svg_data.as_bytes(), text, *foreground, *background, config),
+        } => render_svg_with_text(svg_data, text, *foreground, *background, config),
         Button::CustomImage { image } => Ok(image.clone()),
+        Button::Image {
+            image,
+            text,
+            background,
+            foreground,
+            tint,
+        } => render_image(image, text, *foreground, *background, *tint, config),
         Button::Gradient {
             start_color,
             end_color,
@@ -36,6 +67,13 @@ pub fn render_button(
             foreground,
             background,
         } => render_text(text, *foreground, *background, config),
+        Button::Marquee {
+            svg_data,
+            text,
+            offset,
+            background,
+            foreground,
+        } => render_marquee(svg_data.as_deref(), text, *offset, *foreground, *background, config),
     }
 }
 
@@ -83,37 +121,37 @@ fn render_text(
         }
     }
 
-    let font = FontRef::try_from_slice(config.font_data).map_err(|_| "Failed to load font")?;
-    let scale = PxScale::from(config.font_scale);
-    let text_size = text_size(scale, &font, text);
-
-    draw_text_mut(
-        &mut image,
-        foreground,
-        ((config.width as i32 - text_size.0 as i32) / 2) as i32,
-        (config.height as i32 - text_size.1 as i32 - 6) as i32,
-        scale,
-        &font,
-        text,
-    );
+    let font = label_font(config)?;
+    TextBlock::layout(text, font, config).draw(&mut image, foreground, font, config, false);
 
     Ok(image)
 }
 
 fn render_svg(
-    svg_data: &[u8],
+    svg_data: &str,
     config: &RenderConfig,
     background: Color,
     foreground: Color,
 ) -> Result<DynamicImage, Box<dyn std::error::Error>> {
-    let opt = usvg::Options::default();
-    let tree = Tree::from_data(svg_data, &opt)?;
+    let tree = match &config.cache {
+        Some(cache) => cache.tree(svg_data)?,
+        None => Arc::new(Tree::from_data(svg_data.as_bytes(), &usvg::Options::default())?),
+    };
     let mut raw_pixmap =
         Pixmap::new(config.width, config.height).ok_or("Failed to create pixmap")?;
     raw_pixmap.fill(Color::from_rgba8(0, 0, 0, 0));
 
-    let scale = config.width as f32 / 40.0;
-    let transform = Transform::from_scale(scale, scale).pre_translate(8.0, 6.0);
+    // Size the icon box from the shorter side, so that it also fits on
+    // keys that are wider than they are tall, such as touch strip
+    // segments. The icon is scaled to fit the box and centered in it,
+    // whatever the size and aspect ratio of its view box.
+    let layout = &config.layout;
+    let icon_box = layout.icon_box(config.width, config.height);
+    let size = tree.size();
+    let scale = (icon_box / size.width()).min(icon_box / size.height());
+    let x = (config.width as f32 - size.width() * scale) / 2.0 + layout.icon_offset.0 as f32;
+    let y = layout.icon_top(config.height) + (icon_box - size.height() * scale) / 2.0;
+    let transform = Transform::from_translate(x, y).pre_scale(scale, scale);
     resvg::render(&tree, transform, &mut raw_pixmap.as_mut());
 
     let mut pixmap = Pixmap::new(config.width, config.height).ok_or("Failed to create pixmap")?;
@@ -159,19 +197,97 @@ fn render_svg(
 }
 
 fn render_svg_with_text(
-    svg_data: &[u8],
+    svg_data: &str,
     text: &str,
     foreground: Color,
     background: Color,
     config: &RenderConfig,
 ) -> Result<DynamicImage, Box<dyn std::error::Error>> {
     let mut img = render_svg(svg_data, config, background, foreground)?;
+    if config.layout.icon_only {
+        return Ok(img);
+    }
 
-    let font = FontRef::try_from_slice(config.font_data).map_err(|_| "Failed to load font")?;
-    let scale = PxScale::from(config.font_scale);
-    let text_size = text_size(scale, &font, text);
+    let font = label_font(config)?;
+    TextBlock::layout_under_icon(text, font, config).draw(
+        &mut img,
+        Rgba([
+            (foreground.red() * 255.0) as u8,
+            (foreground.green() * 255.0) as u8,
+            (foreground.blue() * 255.0) as u8,
+            255,
+        ]),
+        font,
+        config,
+        true,
+    );
+
+    Ok(img)
+}
+
+fn render_image(
+    image: &RasterImage,
+    text: &str,
+    foreground: Color,
+    background: Color,
+    tint: Option<Color>,
+    config: &RenderConfig,
+) -> Result<DynamicImage, Box<dyn std::error::Error>> {
+    let background = Rgba([
+        (background.red() * 255.0) as u8,
+        (background.green() * 255.0) as u8,
+        (background.blue() * 255.0) as u8,
+        255,
+    ]);
+    let scaled = match image.fit() {
+        ImageFit::Fit => image.image().resize(
+            config.width,
+            config.height,
+            imageops::FilterType::Triangle,
+        ),
+        ImageFit::Fill => image.image().resize_to_fill(
+            config.width,
+            config.height,
+            imageops::FilterType::Triangle,
+        ),
+    };
+    let mut canvas = RgbaImage::from_pixel(config.width, config.height, background);
+    imageops::overlay(
+        &mut canvas,
+        &scaled.to_rgba8(),
+        (config.width as i64 - scaled.width() as i64) / 2,
+        (config.height as i64 - scaled.height() as i64) / 2,
+    );
+    if let Some(tint) = tint {
+        let tint = Rgba([
+            (tint.red() * 255.0) as u8,
+            (tint.green() * 255.0) as u8,
+            (tint.blue() * 255.0) as u8,
+            255,
+        ]);
+        for pixel in canvas.pixels_mut() {
+            *pixel = weighted_sum(*pixel, tint, 0.5, 0.5);
+        }
+    }
+    if text.is_empty() || config.layout.icon_only {
+        return Ok(DynamicImage::ImageRgba8(canvas));
+    }
 
-    draw_text_mut(
+    // Draw the caption on a band of the background colour, so that it
+    // stays readable on busy images.
+    let font = label_font(config)?;
+    let block = TextBlock::layout(text, font, config);
+    let (top, height) = block.extent(font, config, config.height as i32, true);
+    let margin = config.layout.padding.1 as i32 / 2;
+    let band = (top - margin).max(0)..(top + height + margin).min(config.height as i32);
+    for y in band {
+        for x in 0..config.width {
+            let pixel = canvas.get_pixel_mut(x, y as u32);
+            *pixel = weighted_sum(*pixel, background, 0.4, 0.6);
+        }
+    }
+    let mut img = DynamicImage::ImageRgba8(canvas);
+    block.draw(
         &mut img,
         Rgba([
             (foreground.red() * 255.0) as u8,
@@ -179,16 +295,56 @@ fn render_svg_with_text(
             (foreground.blue() * 255.0) as u8,
             255,
         ]),
-        ((config.width as i32 - text_size.0 as i32) / 2) as i32,
-        (config.height as i32 - text_size.1 as i32 - 6) as i32,
-        scale,
-        &font,
+        font,
+        config,
+        true,
+    );
+
+    Ok(img)
+}
+
+fn render_marquee(
+    svg_data: Option<&str>,
+    text: &str,
+    offset: u32,
+    foreground: Color,
+    background: Color,
+    config: &RenderConfig,
+) -> Result<DynamicImage, Box<dyn std::error::Error>> {
+    let mut img = match svg_data {
+        Some(svg_data) => render_svg(svg_data, config, background, foreground)?,
+        None => render_text("", foreground, background, config)?,
+    };
+    if svg_data.is_some() && config.layout.icon_only {
+        return Ok(img);
+    }
+
+    let font = label_font(config)?;
+    draw_marquee(
+        &mut img,
+        Rgba([
+            (foreground.red() * 255.0) as u8,
+            (foreground.green() * 255.0) as u8,
+            (foreground.blue() * 255.0) as u8,
+            255,
+        ]),
+        font,
+        config,
         text,
+        offset,
+        svg_data.is_some(),
     );
 
     Ok(img)
 }
 
+fn label_font(config: &RenderConfig) -> Result<&FontChain, Box<dyn std::error::Error>> {
+    if config.font.is_empty() {
+        return Err("Failed to load font".into());
+    }
+    Ok(&config.font)
+}
+
 fn render_gradient(
     start_color: Rgba<u8>,
     end_color: Rgba<u8>,
@@ -211,3 +367,152 @@ fn interpolate(start: u8, end: u8, x: u32, y: u32, config: &RenderConfig) -> u8
     let t = (x as f32 / config.width as f32 + y as f32 / config.height as f32) / 2.0;
     (start as f32 * (1.0 - t) + end as f32 * t) as u8
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::button::{text::marquee_cycle, Layout};
+    use image::GenericImageView;
+
+    /// Render an icon filling a view box of the given size, and get the
+    /// bounds of the drawn pixels as left, top, right and bottom.
+    fn icon_bounds(view_box: (u32, u32), config: &RenderConfig) -> (u32, u32, u32, u32) {
+        let svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {w} {h}"><rect width="{w}" height="{h}"/></svg>"#,
+            w = view_box.0,
+            h = view_box.1
+        );
+        let image = render_svg(&svg, config, Color::BLACK, Color::WHITE).unwrap();
+        let ink: Vec<(u32, u32)> = image
+            .pixels()
+            .filter(|(_, _, pixel)| pixel[0] > 128)
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        let left = ink.iter().map(|(x, _)| *x).min().unwrap();
+        let top = ink.iter().map(|(_, y)| *y).min().unwrap();
+        let right = ink.iter().map(|(x, _)| *x).max().unwrap() + 1;
+        let bottom = ink.iter().map(|(_, y)| *y).max().unwrap() + 1;
+        (left, top, right, bottom)
+    }
+
+    #[test]
+    fn icon_fits_its_box() {
+        let config = RenderConfig::default().resized(100, 100);
+        assert_eq!(icon_bounds((24, 24), &config), (20, 15, 80, 75));
+        assert_eq!(icon_bounds((48, 48), &config), (20, 15, 80, 75));
+        assert_eq!(icon_bounds((48, 24), &config), (20, 30, 80, 60));
+        assert_eq!(icon_bounds((12, 24), &config), (35, 15, 65, 75));
+    }
+
+    /// Get the runs of rows that have foreground pixels.
+    fn ink_rows(image: &DynamicImage) -> Vec<(u32, u32)> {
+        let mut runs: Vec<(u32, u32)> = Vec::new();
+        for y in 0..image.height() {
+            let ink = (0..image.width()).any(|x| image.get_pixel(x, y)[0] > 128);
+            match runs.last_mut() {
+                Some((_, end)) if ink && *end == y => *end = y + 1,
+                _ if ink => runs.push((y, y + 1)),
+                _ => {}
+            }
+        }
+        runs
+    }
+
+    #[test]
+    fn wrapped_label_stays_below_icon() {
+        let square = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24"><rect width="24" height="24"/></svg>"#;
+        let label = "Microphone Volume";
+
+        let config = RenderConfig::default();
+        let image =
+            render_svg_with_text(square, label, Color::WHITE, Color::BLACK, &config).unwrap();
+        let runs = ink_rows(&image);
+        assert_eq!(runs.len(), 2, "icon and label overlap: {:?}", runs);
+        assert_eq!(runs[0], (11, 54));
+        assert!(runs[1].0 > 54, "{:?}", runs);
+
+        // With a smaller icon, there is room for the label to wrap.
+        let config = config.with_layout(Layout::new().with_icon_size(0.3));
+        let image =
+            render_svg_with_text(square, label, Color::WHITE, Color::BLACK, &config).unwrap();
+        let runs = ink_rows(&image);
+        assert_eq!(runs.len(), 3, "{:?}", runs);
+        assert_eq!(runs[0], (11, 32));
+        assert!(runs[1].0 > 32, "{:?}", runs);
+    }
+
+    #[test]
+    fn icon_is_placed_from_key_height() {
+        let config = RenderConfig::default()
+            .resized(200, 100)
+            .with_layout(Layout::new().with_icon_size(0.5).with_icon_offset(10, -5));
+        assert_eq!(icon_bounds((24, 24), &config), (85, 10, 135, 60));
+    }
+
+    #[test]
+    fn marquee_scrolls_and_repeats() {
+        let config = RenderConfig::default();
+        let text = "Now playing: a rather long track title";
+        assert_eq!(marquee_cycle("Play", &config), None);
+        let cycle = marquee_cycle(text, &config).unwrap();
+        assert!(cycle > config.width);
+
+        let marquee = |offset| {
+            let button = Button::Marquee {
+                svg_data: None,
+                text: text.to_string(),
+                offset,
+                background: Color::BLACK,
+                foreground: Color::WHITE,
+            };
+            render_button(&button, &config).unwrap()
+        };
+        assert!(marquee(0) == marquee(cycle));
+        assert!(marquee(0) != marquee(cycle / 2));
+        assert!(marquee(cycle / 2) == marquee(cycle + cycle / 2));
+    }
+
+    /// Render a wide red image on a black key.
+    fn render_wide(fit: ImageFit, text: &str, tint: Option<Color>) -> DynamicImage {
+        let image = RgbaImage::from_pixel(40, 20, Rgba([255, 0, 0, 255]));
+        let button = Button::Image {
+            image: RasterImage::new(DynamicImage::ImageRgba8(image)).with_fit(fit),
+            text: text.to_string(),
+            background: Color::BLACK,
+            foreground: Color::WHITE,
+            tint,
+        };
+        render_button(&button, &RenderConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn images_fit_or_fill_the_key() {
+        let fitted = render_wide(ImageFit::Fit, "", None);
+        assert_eq!(fitted.dimensions(), (72, 72));
+        assert_eq!(fitted.get_pixel(36, 2), Rgba([0, 0, 0, 255]));
+        assert_eq!(fitted.get_pixel(36, 36), Rgba([255, 0, 0, 255]));
+        assert_eq!(fitted.get_pixel(36, 69), Rgba([0, 0, 0, 255]));
+
+        let filled = render_wide(ImageFit::Fill, "", None);
+        assert!(filled
+            .pixels()
+            .all(|(_, _, pixel)| pixel == Rgba([255, 0, 0, 255])));
+
+        let tinted = render_wide(ImageFit::Fill, "", Some(Color::WHITE));
+        assert_eq!(tinted.get_pixel(36, 36), Rgba([255, 127, 127, 255]));
+    }
+
+    #[test]
+    fn caption_is_drawn_on_a_band() {
+        let plain = render_wide(ImageFit::Fill, "", None);
+        let captioned = render_wide(ImageFit::Fill, "Album", None);
+        assert_eq!(captioned.get_pixel(36, 10), plain.get_pixel(36, 10));
+        let changed: Vec<u32> = (0..72)
+            .filter(|y| (0..72).any(|x| captioned.get_pixel(x, *y) != plain.get_pixel(x, *y)))
+            .collect();
+        assert!(!changed.is_empty());
+        assert!(*changed.first().unwrap() > 36, "{:?}", changed);
+        let band = *changed.first().unwrap();
+        assert!(captioned.get_pixel(0, band)[0] < 255);
+    }
+}
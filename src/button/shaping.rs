@@ -0,0 +1,158 @@
+//! Text shaping for Stream Deck button labels.
+//!
+//! This module shapes lines of text with rustybuzz, so that ligatures,
+//! kerning, joining scripts and right-to-left text are laid out properly.
+//! It is only available with the `shaping` feature.
+
+use std::ops::Range;
+
+use ab_glyph::{Font, GlyphId, PxScale, ScaleFont};
+use rustybuzz::{Direction, Face, UnicodeBuffer};
+use unicode_bidi::BidiInfo;
+
+use super::font::FontChain;
+
+/// A glyph placed on a line of shaped text.
+pub(crate) struct ShapedGlyph {
+    /// The index of the font of the glyph in the chain.
+    pub(crate) font: usize,
+    /// The glyph in its font.
+    pub(crate) id: GlyphId,
+    /// The position of the glyph origin from the start of the line.
+    pub(crate) x: f32,
+    /// The offset of the glyph above the baseline.
+    pub(crate) y: f32,
+}
+
+/// Shape a line of text.
+///
+/// Returns the glyphs in visual order, from left to right, and the width
+/// of the line.
+pub(crate) fn shape_line(
+    fonts: &FontChain,
+    scale: PxScale,
+    text: &str,
+) -> (Vec<ShapedGlyph>, f32) {
+    let mut glyphs = Vec::new();
+    let mut pen = 0.0;
+    let bidi = BidiInfo::new(text, None);
+    for paragraph in &bidi.paragraphs {
+        let (levels, runs) = bidi.visual_runs(paragraph, paragraph.range.clone());
+        for run in runs {
+            let rtl = levels[run.start].is_rtl();
+            let mut pieces = font_runs(fonts, text, run);
+            if rtl {
+                pieces.reverse();
+            }
+            for (font, range) in pieces {
+                pen = shape_run(fonts, font, scale, &text[range], rtl, pen, &mut glyphs);
+            }
+        }
+    }
+    (glyphs, pen)
+}
+
+/// Split a run of text into pieces drawn with a single font.
+///
+/// A character stays in the current piece as long as its font has a glyph
+/// for it, so spaces and combining marks do not break shaping.
+fn font_runs(fonts: &FontChain, text: &str, run: Range<usize>) -> Vec<(usize, Range<usize>)> {
+    let mut pieces: Vec<(usize, Range<usize>)> = Vec::new();
+    for (offset, c) in text[run.clone()].char_indices() {
+        let start = run.start + offset;
+        let end = start + c.len_utf8();
+        match pieces.last_mut() {
+            Some((font, range)) if fonts.has_glyph(*font, c) => range.end = end,
+            _ => pieces.push((fonts.font_index(c), start..end)),
+        }
+    }
+    pieces
+}
+
+/// Shape a piece of text drawn with a single font, starting at `pen`.
+///
+/// Returns the position after the last glyph.
+fn shape_run(
+    fonts: &FontChain,
+    font: usize,
+    scale: PxScale,
+    text: &str,
+    rtl: bool,
+    pen: f32,
+    glyphs: &mut Vec<ShapedGlyph>,
+) -> f32 {
+    let font_arc = &fonts.fonts()[font];
+    let Some(face) = Face::from_slice(font_arc.font_data(), 0) else {
+        return pen;
+    };
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.set_direction(if rtl {
+        Direction::RightToLeft
+    } else {
+        Direction::LeftToRight
+    });
+    buffer.guess_segment_properties();
+    let output = rustybuzz::shape(&face, &[], buffer);
+
+    let scaled = font_arc.as_scaled(scale);
+    let (h_factor, v_factor) = (scaled.h_scale_factor(), scaled.v_scale_factor());
+    let mut pen = pen;
+    for (info, position) in output.glyph_infos().iter().zip(output.glyph_positions()) {
+        glyphs.push(ShapedGlyph {
+            font,
+            id: GlyphId(info.glyph_id as u16),
+            x: pen + position.x_offset as f32 * h_factor,
+            y: position.y_offset as f32 * v_factor,
+        });
+        pen += position.x_advance as f32 * h_factor;
+    }
+    pen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCALE: PxScale = PxScale { x: 20.0, y: 20.0 };
+
+    /// Get the glyph of a character in the first font of a chain.
+    fn glyph(fonts: &FontChain, c: char) -> GlyphId {
+        fonts.fonts()[0].glyph_id(c)
+    }
+
+    #[test]
+    fn latin_is_laid_out_left_to_right() {
+        let fonts = FontChain::default();
+        let (glyphs, width) = shape_line(&fonts, SCALE, "Label");
+        let ids: Vec<GlyphId> = glyphs.iter().map(|glyph| glyph.id).collect();
+        let expected: Vec<GlyphId> = "Label".chars().map(|c| glyph(&fonts, c)).collect();
+        assert_eq!(ids, expected);
+        assert!(glyphs.windows(2).all(|pair| pair[0].x < pair[1].x));
+
+        let scaled = fonts.fonts()[0].as_scaled(SCALE);
+        let advances: f32 = expected.iter().map(|id| scaled.h_advance(*id)).sum();
+        assert!((width - advances).abs() < 1.0, "{} != {}", width, advances);
+        assert_eq!(fonts.text_size(SCALE, "Label").0, width as u32);
+    }
+
+    #[test]
+    fn right_to_left_runs_are_reversed() {
+        let fonts = FontChain::default();
+        let (a, b, c) = (glyph(&fonts, 'a'), glyph(&fonts, 'b'), glyph(&fonts, 'c'));
+        let (glyphs, _) = shape_line(&fonts, SCALE, "c \u{202E}ab\u{202C}");
+        let ids: Vec<GlyphId> = glyphs
+            .iter()
+            .map(|glyph| glyph.id)
+            .filter(|id| [a, b, c].contains(id))
+            .collect();
+        assert_eq!(ids, [c, b, a]);
+    }
+
+    #[test]
+    fn empty_line_has_no_glyphs() {
+        let (glyphs, width) = shape_line(&FontChain::default(), SCALE, "");
+        assert!(glyphs.is_empty());
+        assert_eq!(width, 0.0);
+    }
+}
@@ -0,0 +1,211 @@
+//! Text layout for Stream Deck buttons.
+//!
+//! This module wraps button labels to the width of a key, limits them to
+//! a maximum number of lines and shrinks the font when configured to. It
+//! also draws the single-line labels of scrolling buttons.
+
+use ab_glyph::PxScale;
+use image::{DynamicImage, Rgba};
+
+use super::font::FontChain;
+use super::types::RenderConfig;
+
+/// The step by which the font scale is reduced when shrinking a label.
+const SHRINK_STEP: f32 = 1.0;
+
+/// The space between the end of a scrolling label and its next repetition.
+const MARQUEE_GAP: u32 = 24;
+
+/// A label laid out to fit on a button.
+pub(crate) struct TextBlock {
+    /// The lines of the label, from top to bottom.
+    lines: Vec<String>,
+    /// The font scale the lines were laid out with.
+    scale: PxScale,
+}
+
+impl TextBlock {
+    /// Lay out a label for a button rendered with a config.
+    ///
+    /// The label is split on `\n` and wrapped at word boundaries. When it
+    /// needs more than `max_lines` lines, the font is shrunk down to the
+    /// minimum scale of the config, and the last line is ellipsized if the
+    /// label still does not fit.
+    pub(crate) fn layout(text: &str, font: &FontChain, config: &RenderConfig) -> Self {
+        TextBlock::fit(text, font, config, None)
+    }
+
+    /// Lay out a label drawn under an icon.
+    ///
+    /// Unlike `layout`, the label only gets as many lines as fit between
+    /// the icon box and the bottom padding of the key, and at least one, so
+    /// that wrapped labels do not cover the icon.
+    pub(crate) fn layout_under_icon(text: &str, font: &FontChain, config: &RenderConfig) -> Self {
+        let layout = &config.layout;
+        let icon_bottom =
+            layout.icon_top(config.height) + layout.icon_box(config.width, config.height);
+        let space = config.height as f32 - layout.padding.1 as f32 - icon_bottom;
+        TextBlock::fit(text, font, config, Some(space))
+    }
+
+    /// Lay out a label, limiting it to the lines that fit in the given
+    /// height, if any.
+    fn fit(text: &str, font: &FontChain, config: &RenderConfig, space: Option<f32>) -> Self {
+        let max_width = config.width.saturating_sub(2 * config.layout.padding.0);
+        let max_lines = config.max_lines.max(1);
+        let min_scale = config
+            .min_font_scale
+            .unwrap_or(config.font_scale)
+            .min(config.font_scale);
+
+        let mut scale = config.font_scale;
+        loop {
+            let px_scale = PxScale::from(scale);
+            let max_lines = match space {
+                Some(space) => max_lines.min((space / font.line_height(px_scale)) as usize).max(1),
+                None => max_lines,
+            };
+            let mut lines: Vec<String> = text
+                .split('\n')
+                .flat_map(|paragraph| wrap(paragraph, font, px_scale, max_width))
+                .collect();
+            let overflows = lines.len() > max_lines;
+            if !overflows || scale <= min_scale {
+                if overflows {
+                    lines.truncate(max_lines);
+                    let last = lines.pop().unwrap_or_default();
+                    lines.push(ellipsize(&last, font, px_scale, max_width));
+                }
+                return TextBlock {
+                    lines,
+                    scale: px_scale,
+                };
+            }
+            scale = (scale - SHRINK_STEP).max(min_scale);
+        }
+    }
+
+    /// Get the top and the height of the label on a key of the given
+    /// height.
+    pub(crate) fn extent(
+        &self,
+        font: &FontChain,
+        config: &RenderConfig,
+        key_height: i32,
+        has_icon: bool,
+    ) -> (i32, i32) {
+        let line_height = font.line_height(self.scale).ceil() as i32;
+        let last_height = self
+            .lines
+            .last()
+            .map(|line| font.text_size(self.scale, line).1 as i32)
+            .unwrap_or(0);
+        let block_height = (self.lines.len() as i32 - 1) * line_height + last_height;
+        let top = config.layout.text_top(has_icon, key_height, block_height);
+        (top, block_height)
+    }
+
+    /// Draw the label centred horizontally on an image, at the position
+    /// given by the layout of the config.
+    pub(crate) fn draw(
+        &self,
+        image: &mut DynamicImage,
+        color: Rgba<u8>,
+        font: &FontChain,
+        config: &RenderConfig,
+        has_icon: bool,
+    ) {
+        let width = image.width() as i32;
+        let line_height = font.line_height(self.scale).ceil() as i32;
+        let (top, _) = self.extent(font, config, image.height() as i32, has_icon);
+
+        for (i, line) in self.lines.iter().enumerate() {
+            let line_width = font.text_size(self.scale, line).0 as i32;
+            font.draw_text(
+                image,
+                color,
+                (width - line_width) / 2,
+                top + i as i32 * line_height,
+                self.scale,
+                line,
+            );
+        }
+    }
+}
+
+/// Wrap a paragraph at word boundaries, breaking words that are too long
+/// to fit on a line of their own.
+fn wrap(paragraph: &str, font: &FontChain, scale: PxScale, max_width: u32) -> Vec<String> {
+    let fits = |text: &str| font.text_size(scale, text).0 <= max_width;
+    let mut lines = Vec::new();
+    let mut current = String::new();
+
+    for word in paragraph.split_whitespace() {
+        let candidate = if current.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", current, word)
+        };
+        if fits(&candidate) {
+            current = candidate;
+            continue;
+        }
+        if !current.is_empty() {
+            lines.push(std::mem::take(&mut current));
+        }
+        for c in word.chars() {
+            current.push(c);
+            if !fits(&current) && current.chars().count() > 1 {
+                current.pop();
+                lines.push(std::mem::replace(&mut current, c.to_string()));
+            }
+        }
+    }
+    lines.push(current);
+    lines
+}
+
+/// Shorten a line until it fits with an ellipsis appended.
+fn ellipsize(line: &str, font: &FontChain, scale: PxScale, max_width: u32) -> String {
+    let mut line = line.trim_end().to_string();
+    loop {
+        let candidate = format!("{}…", line);
+        if line.is_empty() || font.text_size(scale, &candidate).0 <= max_width {
+            return candidate;
+        }
+        line.pop();
+        line.truncate(line.trim_end().len());
+    }
+}
+
+/// Get the distance a label scrolls before it repeats.
+///
+/// Returns `None` if the label fits on a single line and does not need to
+/// scroll.
+pub(crate) fn marquee_cycle(text: &str, config: &RenderConfig) -> Option<u32> {
+    let width = config.font.text_size(PxScale::from(config.font_scale), text).0;
+    let max_width = config.width.saturating_sub(2 * config.layout.padding.0);
+    (width > max_width).then_some(width + MARQUEE_GAP)
+}
+
+/// Draw a single-line label on an image, scrolled to the left by `offset`
+/// pixels and repeated after its end.
+pub(crate) fn draw_marquee(
+    image: &mut DynamicImage,
+    color: Rgba<u8>,
+    font: &FontChain,
+    config: &RenderConfig,
+    text: &str,
+    offset: u32,
+    has_icon: bool,
+) {
+    let scale = PxScale::from(config.font_scale);
+    let (width, height) = font.text_size(scale, text);
+    let cycle = (width + MARQUEE_GAP) as i32;
+    let x = config.layout.padding.0 as i32 - (offset as i32 % cycle);
+    let y = config
+        .layout
+        .text_top(has_icon, image.height() as i32, height as i32);
+    font.draw_text(image, color, x, y, scale, text);
+    font.draw_text(image, color, x + cycle, y, scale, text);
+}
@@ -1,19 +1,27 @@
+use std::sync::Arc;
+
 use image::DynamicImage;
 use image::Rgba;
 use resvg::tiny_skia::Color;
 
+use super::cache::RenderCache;
+use super::font::{FontChain, FontWeight};
+use super::icon::Icon;
+use super::layout::Layout;
+use super::raster::RasterImage;
+
 /// Represents different types of buttons for the Stream Deck
 #[derive(Clone)]
 pub enum Button {
     /// A button with just an SVG icon
     Icon {
-        svg_data: &'static str,
+        svg_data: Icon,
         background: Color,
         foreground: Color,
     },
     /// A button with an SVG icon and text label
     IconWithText {
-        svg_data: &'static str,
+        svg_data: Icon,
         text: String,
         background: Color,
         foreground: Color,
@@ -31,24 +39,143 @@ pub enum Button {
         start_color: Rgba<u8>,
         end_color: Rgba<u8>,
     },
+    /// A button with a raster image and an optional caption
+    Image {
+        image: RasterImage,
+        text: String,
+        background: Color,
+        foreground: Color,
+        /// A colour blended over the image, such as the background of a
+        /// pressed key.
+        tint: Option<Color>,
+    },
+    /// A button with a single-line label scrolled horizontally
+    Marquee {
+        svg_data: Option<Icon>,
+        text: String,
+        offset: u32,
+        background: Color,
+        foreground: Color,
+    },
 }
 
+/// The default maximum number of lines of a label.
+pub const DEFAULT_MAX_LINES: usize = 2;
+
 /// Configuration for rendering buttons
+#[derive(Clone)]
 pub struct RenderConfig {
     pub(crate) width: u32,
     pub(crate) height: u32,
-    pub(crate) font_data: &'static [u8],
+    pub(crate) font: FontChain,
+    pub(crate) weight_fonts: Vec<(FontWeight, FontChain)>,
     pub(crate) font_scale: f32,
+    pub(crate) max_lines: usize,
+    pub(crate) min_font_scale: Option<f32>,
+    pub(crate) layout: Layout,
+    pub(crate) cache: Option<Arc<RenderCache>>,
 }
 
 impl RenderConfig {
     /// Create a new render config
+    ///
+    /// Rendering labels fails if `font_data` is not a valid font.
     pub fn new(width: u32, height: u32, font_data: &'static [u8], font_scale: f32) -> Self {
         RenderConfig {
             width,
             height,
-            font_data,
+            font: FontChain::from_static(font_data)
+                .unwrap_or_else(|_| FontChain::from_fonts(Vec::new())),
+            weight_fonts: Vec::new(),
             font_scale,
+            max_lines: DEFAULT_MAX_LINES,
+            min_font_scale: None,
+            layout: Layout::default(),
+            cache: Some(Arc::new(RenderCache::default())),
+        }
+    }
+
+    /// Set the fonts labels are drawn with
+    pub fn with_font(self, font: FontChain) -> Self {
+        RenderConfig { font, ..self }
+    }
+
+    /// Set the fonts labels of the given weight are drawn with
+    ///
+    /// Buttons pick a weight with `view::Button::with_font_weight`.
+    pub fn with_weight_font(self, weight: FontWeight, font: FontChain) -> Self {
+        let mut weight_fonts = self.weight_fonts;
+        weight_fonts.retain(|(existing, _)| *existing != weight);
+        weight_fonts.push((weight, font));
+        RenderConfig {
+            weight_fonts,
+            ..self
+        }
+    }
+
+    /// Get the fonts labels are drawn with
+    pub fn font(&self) -> &FontChain {
+        &self.font
+    }
+
+    /// Get the fonts labels of the given weight are drawn with, if set
+    pub fn weight_font(&self, weight: FontWeight) -> Option<&FontChain> {
+        self.weight_fonts
+            .iter()
+            .find(|(existing, _)| *existing == weight)
+            .map(|(_, font)| font)
+    }
+
+    /// Set the layout of labels and icons
+    pub fn with_layout(self, layout: Layout) -> Self {
+        RenderConfig { layout, ..self }
+    }
+
+    /// Set the maximum number of lines a label is wrapped to
+    ///
+    /// Labels that need more lines are ellipsized. Labels under an icon
+    /// are also limited to the lines that fit below the icon.
+    pub fn with_max_lines(self, max_lines: usize) -> Self {
+        RenderConfig { max_lines, ..self }
+    }
+
+    /// Shrink labels that do not fit, down to the given font scale
+    pub fn with_min_font_scale(self, min_font_scale: f32) -> Self {
+        RenderConfig {
+            min_font_scale: Some(min_font_scale),
+            ..self
+        }
+    }
+
+    /// Use the given cache for parsed icons and rendered buttons
+    ///
+    /// The cache is shared with every clone of the config.
+    pub fn with_cache(self, cache: Arc<RenderCache>) -> Self {
+        RenderConfig {
+            cache: Some(cache),
+            ..self
+        }
+    }
+
+    /// Render every button from scratch, without a cache
+    pub fn without_cache(self) -> Self {
+        RenderConfig {
+            cache: None,
+            ..self
+        }
+    }
+
+    /// Get the cache used for parsed icons and rendered buttons
+    pub fn cache(&self) -> Option<&Arc<RenderCache>> {
+        self.cache.as_ref()
+    }
+
+    /// Create a copy of the config with a different size
+    pub(crate) fn resized(&self, width: u32, height: u32) -> Self {
+        RenderConfig {
+            width,
+            height,
+            ..self.clone()
         }
     }
 }
@@ -58,8 +185,13 @@ impl Default for RenderConfig {
         RenderConfig {
             width: 72,
             height: 72,
-            font_data: include_bytes!("../../fonts/Roboto-Medium.ttf"),
+            font: FontChain::default(),
+            weight_fonts: Vec::new(),
             font_scale: 14.0,
+            max_lines: DEFAULT_MAX_LINES,
+            min_font_scale: None,
+            layout: Layout::default(),
+            cache: Some(Arc::new(RenderCache::default())),
         }
     }
 }
@@ -75,9 +207,9 @@ impl Button {
     }
 
     /// Create a new icon button
-    pub fn icon(svg_data: &'static str, background: Color, foreground: Color) -> Self {
+    pub fn icon(svg_data: impl Into<Icon>, background: Color, foreground: Color) -> Self {
         Button::Icon {
-            svg_data,
+            svg_data: svg_data.into(),
             background,
             foreground,
         }
@@ -85,13 +217,13 @@ impl Button {
 
     /// Create a new icon with text button
     pub fn icon_with_text(
-        svg_data: &'static str,
+        svg_data: impl Into<Icon>,
         text: impl Into<String>,
         background: Color,
         foreground: Color,
     ) -> Self {
         Button::IconWithText {
-            svg_data,
+            svg_data: svg_data.into(),
             text: text.into(),
             background,
             foreground,
@@ -103,6 +235,24 @@ impl Button {
         Button::CustomImage { image }
     }
 
+    /// Create a new raster image button
+    ///
+    /// The caption is drawn over the image unless it is empty.
+    pub fn image(
+        image: impl Into<RasterImage>,
+        text: impl Into<String>,
+        background: Color,
+        foreground: Color,
+    ) -> Self {
+        Button::Image {
+            image: image.into(),
+            text: text.into(),
+            background,
+            foreground,
+            tint: None,
+        }
+    }
+
     /// Create a new gradient button
     pub fn gradient(start_color: Rgba<u8>, end_color: Rgba<u8>) -> Self {
         Button::Gradient {
@@ -110,4 +260,21 @@ impl Button {
             end_color,
         }
     }
+
+    /// Create a new button with a label scrolled by `offset` pixels
+    pub fn marquee(
+        svg_data: Option<Icon>,
+        text: impl Into<String>,
+        offset: u32,
+        background: Color,
+        foreground: Color,
+    ) -> Self {
+        Button::Marquee {
+            svg_data,
+            text: text.into(),
+            offset,
+            background,
+            foreground,
+        }
+    }
 }
@@ -0,0 +1,76 @@
+//! Elgato Stream Deck backend.
+//!
+//! This module implements the device traits for `AsyncStreamDeck`.
+
+use std::sync::Arc;
+
+use elgato_streamdeck::{
+    asynchronous::AsyncDeviceStateReader,
+    images::{convert_image_with_format_async, ImageRect},
+    info::Kind,
+    AsyncStreamDeck, DeviceStateUpdate,
+};
+use image::DynamicImage;
+
+use crate::error::{Error, Result};
+
+use super::{DeckDevice, DeckReader};
+
+#[async_trait::async_trait]
+impl DeckDevice for AsyncStreamDeck {
+    async fn set_button_image(&self, index: u8, image: DynamicImage) -> Result<()> {
+        AsyncStreamDeck::set_button_image(self, index, image).await?;
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<()> {
+        AsyncStreamDeck::flush(self).await?;
+        Ok(())
+    }
+
+    async fn set_brightness(&self, percent: u8) -> Result<()> {
+        AsyncStreamDeck::set_brightness(self, percent).await?;
+        Ok(())
+    }
+
+    fn lcd_size(&self) -> Option<(u32, u32)> {
+        self.kind()
+            .lcd_strip_size()
+            .map(|(width, height)| (width as u32, height as u32))
+    }
+
+    fn encoder_count(&self) -> u8 {
+        self.kind().encoder_count()
+    }
+
+    async fn set_lcd_image(&self, x: u16, y: u16, image: DynamicImage) -> Result<()> {
+        let kind = self.kind();
+        if kind == Kind::Plus {
+            let rect = ImageRect::from_image_async(image)?;
+            self.write_lcd(x, y, &rect).await?;
+            return Ok(());
+        }
+        let format = kind
+            .lcd_image_format()
+            .ok_or_else(|| Error::DeviceError("Device has no touch strip".to_string()))?;
+        if x != 0 || y != 0 {
+            return Err(Error::DeviceError(
+                "Device only supports drawing the whole touch strip".to_string(),
+            ));
+        }
+        let image_data = convert_image_with_format_async(format, image)?;
+        self.write_lcd_fill(&image_data).await?;
+        Ok(())
+    }
+
+    fn reader(&self) -> Arc<dyn DeckReader> {
+        self.get_reader()
+    }
+}
+
+#[async_trait::async_trait]
+impl DeckReader for AsyncDeviceStateReader {
+    async fn read(&self, poll_rate: f32) -> Result<Vec<DeviceStateUpdate>> {
+        Ok(AsyncDeviceStateReader::read(self, poll_rate).await?)
+    }
+}
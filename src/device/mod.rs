@@ -0,0 +1,93 @@
+//! Device abstraction for Stream Deck applications.
+//!
+//! This module provides traits that decouple the display manager and the
+//! run loop from a concrete Stream Deck implementation, so that views can be
+//! driven by hardware or by any other backend.
+
+mod elgato;
+#[cfg(feature = "terminal")]
+mod terminal;
+mod virtual_deck;
+#[cfg(feature = "web")]
+mod web;
+
+// Re-export public items
+#[cfg(feature = "terminal")]
+pub use self::terminal::{TerminalDeck, TerminalReader};
+pub use self::virtual_deck::{Frame, VirtualDeck, VirtualReader};
+#[cfg(feature = "web")]
+pub use self::web::{WebDeck, WebReader};
+
+use std::sync::Arc;
+
+use elgato_streamdeck::DeviceStateUpdate;
+use image::DynamicImage;
+
+use crate::{error::Result, view::Button};
+
+/// A device that can display button images.
+///
+/// This trait is implemented by types that represent a Stream Deck or
+/// something that behaves like one. It covers uploading button images,
+/// flushing them to the display, changing brightness and reading input.
+#[async_trait::async_trait]
+pub trait DeckDevice: Send + Sync + 'static {
+    /// Set the image of the button at the given index.
+    ///
+    /// Changes are not guaranteed to be visible until `flush` is called.
+    async fn set_button_image(&self, index: u8, image: DynamicImage) -> Result<()>;
+
+    /// Describe the button at the given index.
+    ///
+    /// This method is called next to `set_button_image` with the view-level
+    /// button, for backends that can show a label and state on their own.
+    /// The default implementation does nothing.
+    async fn set_button_info(&self, _index: u8, _button: &Button) -> Result<()> {
+        Ok(())
+    }
+
+    /// Flush pending button images to the display.
+    async fn flush(&self) -> Result<()>;
+
+    /// Set the brightness of the display, in percent.
+    async fn set_brightness(&self, percent: u8) -> Result<()>;
+
+    /// Get the size of the touch strip, in pixels.
+    ///
+    /// The default implementation returns `None`, for devices without
+    /// a touch strip.
+    fn lcd_size(&self) -> Option<(u32, u32)> {
+        None
+    }
+
+    /// Get the number of encoders.
+    ///
+    /// The default implementation returns 0, for devices without encoders.
+    fn encoder_count(&self) -> u8 {
+        0
+    }
+
+    /// Draw an image on the touch strip with its top-left corner at the
+    /// given position.
+    ///
+    /// Unlike button images, the image is shown right away. The default
+    /// implementation does nothing.
+    async fn set_lcd_image(&self, _x: u16, _y: u16, _image: DynamicImage) -> Result<()> {
+        Ok(())
+    }
+
+    /// Get a reader for input events.
+    ///
+    /// The run loop calls this once and keeps reading from the returned
+    /// reader, so implementations may keep state in it.
+    fn reader(&self) -> Arc<dyn DeckReader>;
+}
+
+/// A reader for input events of a device.
+#[async_trait::async_trait]
+pub trait DeckReader: Send + Sync + 'static {
+    /// Wait for input and return the resulting state updates.
+    ///
+    /// The poll rate is a hint for backends that have to poll for input.
+    async fn read(&self, poll_rate: f32) -> Result<Vec<DeviceStateUpdate>>;
+}
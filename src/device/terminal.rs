@@ -0,0 +1,519 @@
+//! Terminal Stream Deck simulator.
+//!
+//! This module provides a device that draws the deck in a terminal. Every
+//! key shows a downscaled preview of its image, its label and a border in
+//! the colour of its state. Keyboard keys and mouse clicks are turned into
+//! button presses. It is only available with the `terminal` feature.
+//!
+//! Holding the mouse button holds the key, so long presses and repeats
+//! work with the mouse. Keyboard keys are only held in terminals that
+//! report key releases, such as those supporting the kitty keyboard
+//! protocol, which is also needed for chords. In other terminals, every
+//! keyboard key press is a click.
+
+use std::{
+    io::{stdout, Write},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crossterm::{
+    cursor,
+    event::{
+        self, Event, KeyCode, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags, MouseButton,
+        MouseEventKind, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    execute, queue,
+    style::{self, Color, Stylize},
+    terminal,
+};
+use elgato_streamdeck::DeviceStateUpdate;
+use image::{imageops, DynamicImage};
+use tokio::sync::mpsc;
+use unicode_width::UnicodeWidthChar;
+
+use crate::{
+    error::{Error, Result},
+    view::{Button, ButtonState},
+};
+
+use super::{DeckDevice, DeckReader};
+
+/// Keyboard keys mapped to button indices, in button index order.
+const KEY_BINDINGS: &str = "1234567890qwertyuiopasdfghjklzxcvbnm";
+/// Width of a key in terminal cells, including the border.
+const KEY_WIDTH: u16 = 14;
+/// Height of a key in terminal cells, including the border.
+const KEY_HEIGHT: u16 = 9;
+/// Size of the image preview, in pixels. Two pixels share one cell.
+const PREVIEW_SIZE: u32 = 12;
+/// Terminal row of the first row of keys.
+const TOP: u16 = 2;
+/// Terminal column of the first column of keys.
+const LEFT: u16 = 1;
+/// How long the input thread waits for an event before checking whether
+/// the reader is still in use.
+const INPUT_POLL: Duration = Duration::from_millis(100);
+
+/// The state of a single key as shown in the terminal.
+#[derive(Clone)]
+struct Key {
+    /// The last image uploaded to the key.
+    image: Option<DynamicImage>,
+    /// The label of the key.
+    label: String,
+    /// The state of the key.
+    state: ButtonState,
+    /// Whether the key changed since it was last drawn.
+    dirty: bool,
+}
+
+/// The keys of the simulator and the way they are laid out.
+struct Screen {
+    /// The number of key columns.
+    columns: u8,
+    /// The keys, indexed by button index.
+    keys: Vec<Key>,
+    /// Whether the whole screen must be cleared and redrawn, which is the
+    /// case at startup and after the terminal is resized.
+    redraw_all: bool,
+}
+
+/// A Stream Deck simulator running in a terminal.
+///
+/// This struct implements `DeckDevice` by drawing the keys in the terminal.
+/// The terminal is switched to raw mode and the alternate screen while the
+/// simulator exists, and restored when it is dropped. Pressing Escape or
+/// Ctrl+C makes the reader return an error, which stops the run loop.
+pub struct TerminalDeck {
+    /// The keys of the simulator.
+    screen: Arc<Mutex<Screen>>,
+    /// The reader handed out to the run loop.
+    reader: Arc<TerminalReader>,
+    /// The guard that restores the terminal.
+    _guard: TerminalGuard,
+}
+
+/// Restores the terminal when dropped.
+///
+/// The guard is created as soon as raw mode is enabled, so that the
+/// terminal is also restored when creating the simulator fails halfway.
+struct TerminalGuard {
+    /// Whether the terminal was asked to report key releases.
+    key_releases: bool,
+}
+
+/// The reader of a terminal simulator.
+pub struct TerminalReader {
+    /// The receiver for input events, `None` when the user quits.
+    events: tokio::sync::Mutex<mpsc::UnboundedReceiver<Option<DeviceStateUpdate>>>,
+}
+
+impl TerminalDeck {
+    /// Create a new terminal simulator with the given number of key
+    /// columns and rows.
+    pub fn new(columns: u8, rows: u8) -> Result<Self> {
+        let screen = Arc::new(Mutex::new(Screen {
+            columns: columns.max(1),
+            keys: vec![
+                Key {
+                    image: None,
+                    label: String::new(),
+                    state: ButtonState::Default,
+                    dirty: true,
+                };
+                columns as usize * rows as usize
+            ],
+            redraw_all: true,
+        }));
+
+        terminal::enable_raw_mode()?;
+        let mut guard = TerminalGuard {
+            key_releases: false,
+        };
+        execute!(
+            stdout(),
+            terminal::EnterAlternateScreen,
+            event::EnableMouseCapture,
+            cursor::Hide
+        )?;
+        let key_releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if key_releases {
+            execute!(
+                stdout(),
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+            guard.key_releases = true;
+        }
+        draw(&mut screen.lock().unwrap())?;
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let input_screen = screen.clone();
+        std::thread::spawn(move || read_input(input_screen, sender, key_releases));
+
+        Ok(TerminalDeck {
+            screen,
+            reader: Arc::new(TerminalReader {
+                events: tokio::sync::Mutex::new(receiver),
+            }),
+            _guard: guard,
+        })
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        if self.key_releases {
+            let _ = execute!(stdout(), PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(
+            stdout(),
+            cursor::Show,
+            event::DisableMouseCapture,
+            terminal::LeaveAlternateScreen
+        );
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+#[async_trait::async_trait]
+impl DeckDevice for TerminalDeck {
+    async fn set_button_image(&self, index: u8, image: DynamicImage) -> Result<()> {
+        let mut screen = self.screen.lock().unwrap();
+        let key = screen
+            .keys
+            .get_mut(index as usize)
+            .ok_or(Error::ButtonIndexOutOfBounds(index as usize))?;
+        if key.image.as_ref() != Some(&image) {
+            key.image = Some(image);
+            key.dirty = true;
+        }
+        Ok(())
+    }
+
+    async fn set_button_info(&self, index: u8, button: &Button) -> Result<()> {
+        let mut screen = self.screen.lock().unwrap();
+        let key = screen
+            .keys
+            .get_mut(index as usize)
+            .ok_or(Error::ButtonIndexOutOfBounds(index as usize))?;
+        if key.label != button.get_text() || key.state != button.get_state() {
+            key.label = button.get_text().to_string();
+            key.state = button.get_state();
+            key.dirty = true;
+        }
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<()> {
+        draw(&mut self.screen.lock().unwrap())
+    }
+
+    async fn set_brightness(&self, _percent: u8) -> Result<()> {
+        Ok(())
+    }
+
+    fn reader(&self) -> Arc<dyn DeckReader> {
+        self.reader.clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl DeckReader for TerminalReader {
+    async fn read(&self, _poll_rate: f32) -> Result<Vec<DeviceStateUpdate>> {
+        let mut events = self.events.lock().await;
+        let mut updates = Vec::new();
+        let mut next = events.recv().await;
+        loop {
+            match next {
+                Some(Some(event)) => updates.push(event),
+                _ => return Err(Error::DeviceError("Terminal simulator closed".to_string())),
+            }
+            match events.try_recv() {
+                Ok(event) => next = Some(event),
+                Err(_) => return Ok(updates),
+            }
+        }
+    }
+}
+
+/// Read terminal input and forward it as device events.
+///
+/// This function runs on its own thread, because reading terminal input
+/// blocks. It returns when the user quits or the reader of the simulator
+/// is dropped, which is checked whenever no input arrives for a while.
+/// Without key releases, keyboard keys are released as soon as they are
+/// pressed.
+fn read_input(
+    screen: Arc<Mutex<Screen>>,
+    sender: mpsc::UnboundedSender<Option<DeviceStateUpdate>>,
+    key_releases: bool,
+) {
+    let mut mouse_key = None;
+    let mut held_keys = Vec::new();
+    loop {
+        match event::poll(INPUT_POLL) {
+            Ok(true) => {}
+            Ok(false) if sender.is_closed() => return,
+            Ok(false) => continue,
+            Err(_) => return,
+        }
+        let Ok(event) = event::read() else {
+            return;
+        };
+        let (columns, key_count) = {
+            let screen = screen.lock().unwrap();
+            (screen.columns, screen.keys.len())
+        };
+        let mut updates = Vec::new();
+        match event {
+            Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Esc => updates.push(None),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    updates.push(None)
+                }
+                KeyCode::Char(c) => {
+                    if let Some(index) = key_binding(c, key_count) {
+                        if !held_keys.contains(&index) {
+                            updates.push(Some(DeviceStateUpdate::ButtonDown(index)));
+                            if key_releases {
+                                held_keys.push(index);
+                            } else {
+                                updates.push(Some(DeviceStateUpdate::ButtonUp(index)));
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            },
+            Event::Key(key) if key.kind == KeyEventKind::Release => {
+                if let KeyCode::Char(c) = key.code {
+                    if let Some(index) = key_binding(c, key_count) {
+                        if let Some(position) = held_keys.iter().position(|&held| held == index) {
+                            held_keys.remove(position);
+                            updates.push(Some(DeviceStateUpdate::ButtonUp(index)));
+                        }
+                    }
+                }
+            }
+            Event::Mouse(mouse) => match mouse.kind {
+                MouseEventKind::Down(MouseButton::Left) => {
+                    if let Some(index) = key_at(mouse.column, mouse.row, columns, key_count) {
+                        mouse_key = Some(index);
+                        updates.push(Some(DeviceStateUpdate::ButtonDown(index)));
+                    }
+                }
+                MouseEventKind::Up(MouseButton::Left) => {
+                    if let Some(index) = mouse_key.take() {
+                        updates.push(Some(DeviceStateUpdate::ButtonUp(index)));
+                    }
+                }
+                _ => {}
+            },
+            Event::Resize(_, _) => {
+                let mut screen = screen.lock().unwrap();
+                screen.redraw_all = true;
+                let _ = draw(&mut screen);
+            }
+            _ => {}
+        }
+        for update in updates {
+            let quit = update.is_none();
+            if sender.send(update).is_err() || quit {
+                return;
+            }
+        }
+    }
+}
+
+/// Find the key bound to the given keyboard character.
+fn key_binding(c: char, key_count: usize) -> Option<u8> {
+    KEY_BINDINGS
+        .find(c.to_ascii_lowercase())
+        .filter(|&index| index < key_count)
+        .map(|index| index as u8)
+}
+
+/// Find the key at the given terminal position.
+fn key_at(column: u16, row: u16, columns: u8, key_count: usize) -> Option<u8> {
+    if column < LEFT || row < TOP {
+        return None;
+    }
+    let x = (column - LEFT) / (KEY_WIDTH + 1);
+    let y = (row - TOP) / KEY_HEIGHT;
+    if (column - LEFT) % (KEY_WIDTH + 1) == KEY_WIDTH || x >= columns as u16 {
+        return None;
+    }
+    let index = y as usize * columns as usize + x as usize;
+    (index < key_count).then_some(index as u8)
+}
+
+/// Draw the keys that changed since the last draw to the terminal.
+///
+/// The screen is only cleared when everything must be redrawn, so that
+/// frequent updates such as marquee labels do not make the terminal flicker.
+fn draw(screen: &mut Screen) -> Result<()> {
+    let mut out = stdout();
+    let columns = screen.columns as usize;
+    let redraw_all = std::mem::take(&mut screen.redraw_all);
+    if redraw_all {
+        let rows = screen.keys.len().div_ceil(columns) as u16;
+        queue!(
+            out,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(LEFT, 0),
+            style::Print("Stream Deck simulator".bold()),
+            cursor::MoveTo(LEFT, TOP + rows * KEY_HEIGHT),
+            style::Print(
+                "Press the key shown in the corner or click with the mouse. Esc or Ctrl+C quits."
+                    .dark_grey()
+            )
+        )?;
+    }
+
+    for (index, key) in screen.keys.iter_mut().enumerate() {
+        if !std::mem::take(&mut key.dirty) && !redraw_all {
+            continue;
+        }
+        let x = LEFT + (index % columns) as u16 * (KEY_WIDTH + 1);
+        let y = TOP + (index / columns) as u16 * KEY_HEIGHT;
+        draw_key(&mut out, x, y, index, key)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Draw a single key with its top-left corner at the given position.
+fn draw_key(out: &mut impl Write, x: u16, y: u16, index: usize, key: &Key) -> Result<()> {
+    let border = match key.state {
+        ButtonState::Default => Color::Grey,
+        ButtonState::Pressed => Color::Cyan,
+        ButtonState::Active => Color::Magenta,
+        ButtonState::Inactive => Color::DarkGrey,
+        ButtonState::Error => Color::Red,
+    };
+    let inner = (KEY_WIDTH - 2) as usize;
+    let binding = KEY_BINDINGS
+        .chars()
+        .nth(index)
+        .map(|c| format!(" {} ", c))
+        .unwrap_or_default();
+
+    queue!(
+        out,
+        cursor::MoveTo(x, y),
+        style::PrintStyledContent(
+            format!("┌{}{}┐", binding, "─".repeat(inner - binding.chars().count())).with(border)
+        )
+    )?;
+
+    let preview_rows = (PREVIEW_SIZE / 2) as u16;
+    let preview = key.image.as_ref().map(|image| {
+        imageops::resize(
+            &image.to_rgba8(),
+            PREVIEW_SIZE,
+            PREVIEW_SIZE,
+            imageops::FilterType::Triangle,
+        )
+    });
+    let padding = (inner - PREVIEW_SIZE as usize) / 2;
+    for row in 0..preview_rows {
+        queue!(
+            out,
+            cursor::MoveTo(x, y + 1 + row),
+            style::PrintStyledContent("│".with(border)),
+            style::Print(" ".repeat(padding))
+        )?;
+        for column in 0..PREVIEW_SIZE {
+            match &preview {
+                Some(preview) => {
+                    let top = preview.get_pixel(column, row as u32 * 2);
+                    let bottom = preview.get_pixel(column, row as u32 * 2 + 1);
+                    queue!(
+                        out,
+                        style::PrintStyledContent(
+                            "▀".with(Color::Rgb {
+                                r: top[0],
+                                g: top[1],
+                                b: top[2],
+                            })
+                            .on(Color::Rgb {
+                                r: bottom[0],
+                                g: bottom[1],
+                                b: bottom[2],
+                            })
+                        )
+                    )?;
+                }
+                None => queue!(out, style::Print(" "))?,
+            }
+        }
+        queue!(
+            out,
+            style::Print(" ".repeat(inner - padding - PREVIEW_SIZE as usize)),
+            style::PrintStyledContent("│".with(border))
+        )?;
+    }
+
+    queue!(
+        out,
+        cursor::MoveTo(x, y + 1 + preview_rows),
+        style::PrintStyledContent("│".with(border)),
+        style::Print(fit_label(&key.label, inner)),
+        style::PrintStyledContent("│".with(border)),
+        cursor::MoveTo(x, y + 2 + preview_rows),
+        style::PrintStyledContent(format!("└{}┘", "─".repeat(inner)).with(border))
+    )?;
+    Ok(())
+}
+
+/// Fit a label into the given number of terminal cells, centred.
+///
+/// Control characters such as line breaks are shown as spaces, and the
+/// label is cut by display width, so that wide characters never push the
+/// key border out of place.
+fn fit_label(label: &str, width: usize) -> String {
+    let mut fitted = String::new();
+    let mut used = 0;
+    for c in label.chars() {
+        let c = if c.is_control() { ' ' } else { c };
+        let char_width = c.width().unwrap_or(0);
+        if used + char_width > width {
+            break;
+        }
+        fitted.push(c);
+        used += char_width;
+    }
+    let left = (width - used) / 2;
+    format!("{}{}{}", " ".repeat(left), fitted, " ".repeat(width - used - left))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use unicode_width::UnicodeWidthStr;
+
+    #[test]
+    fn keys_are_bound_in_order() {
+        assert_eq!(key_binding('1', 15), Some(0));
+        assert_eq!(key_binding('Q', 15), Some(10));
+        assert_eq!(key_binding('t', 15), Some(14));
+        assert_eq!(key_binding('y', 15), None);
+        assert_eq!(key_binding('-', 15), None);
+    }
+
+    #[test]
+    fn label_fits_cells() {
+        assert_eq!(fit_label("Mute", 12), "    Mute    ");
+        assert_eq!(fit_label("Two\nlines", 12), " Two lines  ");
+        assert_eq!(fit_label("Tab\there", 8), "Tab here");
+        assert_eq!(fit_label("A much longer label", 12), "A much longe");
+
+        let wide = fit_label("日本語のラベル", 12);
+        assert_eq!(wide.width(), 12);
+        assert_eq!(wide, "日本語のラベ");
+        let odd = fit_label("日本語", 5);
+        assert_eq!(odd.width(), 5);
+        assert_eq!(odd, "日本 ");
+    }
+}
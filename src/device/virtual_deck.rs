@@ -0,0 +1,388 @@
+//! In-memory virtual Stream Deck.
+//!
+//! This module provides a device that keeps uploaded images in memory and
+//! accepts injected input events. It allows running a whole application
+//! without any hardware attached.
+
+use std::sync::{
+    atomic::{AtomicU8, Ordering},
+    Arc, Mutex,
+};
+
+use elgato_streamdeck::DeviceStateUpdate;
+use image::{imageops, DynamicImage};
+use tokio::sync::{mpsc, watch};
+
+use crate::{
+    error::{Error, Result},
+    screenshot::{compose_keys, ScreenshotLayout},
+};
+
+use super::{DeckDevice, DeckReader};
+
+/// A snapshot of all key images taken when the device was flushed.
+#[derive(Clone)]
+pub struct Frame {
+    /// The image of every key, indexed by button index.
+    pub(crate) keys: Vec<Option<DynamicImage>>,
+}
+
+impl Frame {
+    /// Get the image of the key at the given index.
+    ///
+    /// Returns `None` if nothing was uploaded to the key yet.
+    pub fn key(&self, index: u8) -> Option<&DynamicImage> {
+        self.keys.get(index as usize).and_then(|key| key.as_ref())
+    }
+
+    /// Get the images of all keys.
+    pub fn keys(&self) -> &[Option<DynamicImage>] {
+        &self.keys
+    }
+
+    /// Compose the frame into a single image laid out like the device.
+    pub fn screenshot(
+        &self,
+        columns: u32,
+        key_width: u32,
+        key_height: u32,
+        layout: &ScreenshotLayout,
+    ) -> DynamicImage {
+        compose_keys(&self.keys, columns, key_width, key_height, layout)
+    }
+}
+
+/// A virtual Stream Deck.
+///
+/// This struct implements `DeckDevice` entirely in memory. Input events
+/// are injected with `press`, `release` and `click`, and the images sent
+/// by the display manager can be inspected afterwards.
+pub struct VirtualDeck {
+    /// The current image of every key, indexed by button index.
+    keys: Mutex<Vec<Option<DynamicImage>>>,
+    /// The frames recorded on every flush.
+    frames: Mutex<Vec<Frame>>,
+    /// The current brightness.
+    brightness: AtomicU8,
+    /// The sender for injected events.
+    events: mpsc::UnboundedSender<DeviceStateUpdate>,
+    /// The reader handed out to the run loop.
+    reader: Arc<VirtualReader>,
+    /// The number of flushes so far.
+    flushes: watch::Sender<usize>,
+    /// The size of the touch strip, if the deck has one.
+    lcd_size: Option<(u32, u32)>,
+    /// The image shown on the touch strip.
+    lcd: Mutex<Option<DynamicImage>>,
+    /// The number of encoders.
+    encoders: u8,
+}
+
+/// The reader of a virtual Stream Deck.
+pub struct VirtualReader {
+    /// The receiver for injected events.
+    events: tokio::sync::Mutex<mpsc::UnboundedReceiver<DeviceStateUpdate>>,
+}
+
+impl VirtualDeck {
+    /// Create a new virtual Stream Deck with the given number of keys.
+    pub fn new(key_count: u8) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (flushes, _) = watch::channel(0);
+        VirtualDeck {
+            keys: Mutex::new(vec![None; key_count as usize]),
+            frames: Mutex::new(Vec::new()),
+            brightness: AtomicU8::new(100),
+            events: sender,
+            reader: Arc::new(VirtualReader {
+                events: tokio::sync::Mutex::new(receiver),
+            }),
+            flushes,
+            lcd_size: None,
+            lcd: Mutex::new(None),
+            encoders: 0,
+        }
+    }
+
+    /// Give the deck a touch strip of the given size, in pixels.
+    pub fn with_touch_strip(self, width: u32, height: u32) -> Self {
+        VirtualDeck {
+            lcd_size: Some((width, height)),
+            lcd: Mutex::new(Some(DynamicImage::new_rgba8(width, height))),
+            ..self
+        }
+    }
+
+    /// Give the deck the given number of encoders.
+    pub fn with_encoders(self, encoders: u8) -> Self {
+        VirtualDeck { encoders, ..self }
+    }
+
+    /// Get the number of keys.
+    pub fn key_count(&self) -> u8 {
+        self.keys.lock().unwrap().len() as u8
+    }
+
+    /// Inject an input event.
+    pub fn send_event(&self, event: DeviceStateUpdate) {
+        // The receiver lives in the reader, which is owned by this deck.
+        let _ = self.events.send(event);
+    }
+
+    /// Inject a button press.
+    pub fn press(&self, index: u8) {
+        self.send_event(DeviceStateUpdate::ButtonDown(index));
+    }
+
+    /// Inject a button release.
+    pub fn release(&self, index: u8) {
+        self.send_event(DeviceStateUpdate::ButtonUp(index));
+    }
+
+    /// Inject a button press followed by a release.
+    pub fn click(&self, index: u8) {
+        self.press(index);
+        self.release(index);
+    }
+
+    /// Inject an encoder twist.
+    pub fn twist(&self, encoder: u8, ticks: i8) {
+        self.send_event(DeviceStateUpdate::EncoderTwist(encoder, ticks));
+    }
+
+    /// Inject a short touch on the touch strip.
+    pub fn touch(&self, x: u16, y: u16) {
+        self.send_event(DeviceStateUpdate::TouchScreenPress(x, y));
+    }
+
+    /// Get the image shown on the touch strip.
+    ///
+    /// Returns `None` if the deck has no touch strip.
+    pub fn lcd_image(&self) -> Option<DynamicImage> {
+        self.lcd.lock().unwrap().clone()
+    }
+
+    /// Get the last image uploaded to the key at the given index.
+    pub fn key_image(&self, index: u8) -> Option<DynamicImage> {
+        self.keys.lock().unwrap().get(index as usize).cloned().flatten()
+    }
+
+    /// Get the frames recorded on every flush, oldest first.
+    pub fn frames(&self) -> Vec<Frame> {
+        self.frames.lock().unwrap().clone()
+    }
+
+    /// Get the frame recorded on the last flush.
+    pub fn last_frame(&self) -> Option<Frame> {
+        self.frames.lock().unwrap().last().cloned()
+    }
+
+    /// Forget all recorded frames.
+    pub fn clear_frames(&self) {
+        self.frames.lock().unwrap().clear();
+    }
+
+    /// Get the number of flushes so far.
+    pub fn flush_count(&self) -> usize {
+        *self.flushes.borrow()
+    }
+
+    /// Wait until the device has been flushed at least `count` times.
+    pub async fn wait_for_flushes(&self, count: usize) {
+        let mut receiver = self.flushes.subscribe();
+        // The sender is owned by this deck, so the channel cannot close here.
+        let _ = receiver.wait_for(|flushes| *flushes >= count).await;
+    }
+
+    /// Get the current brightness, in percent.
+    pub fn brightness(&self) -> u8 {
+        self.brightness.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait::async_trait]
+impl DeckDevice for VirtualDeck {
+    async fn set_button_image(&self, index: u8, image: DynamicImage) -> Result<()> {
+        let mut keys = self.keys.lock().unwrap();
+        let key = keys
+            .get_mut(index as usize)
+            .ok_or(Error::ButtonIndexOutOfBounds(index as usize))?;
+        *key = Some(image);
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<()> {
+        let frame = Frame {
+            keys: self.keys.lock().unwrap().clone(),
+        };
+        self.frames.lock().unwrap().push(frame);
+        self.flushes.send_modify(|flushes| *flushes += 1);
+        Ok(())
+    }
+
+    async fn set_brightness(&self, percent: u8) -> Result<()> {
+        self.brightness.store(percent.min(100), Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn lcd_size(&self) -> Option<(u32, u32)> {
+        self.lcd_size
+    }
+
+    fn encoder_count(&self) -> u8 {
+        self.encoders
+    }
+
+    async fn set_lcd_image(&self, x: u16, y: u16, image: DynamicImage) -> Result<()> {
+        let mut lcd = self.lcd.lock().unwrap();
+        let lcd = lcd
+            .as_mut()
+            .ok_or_else(|| Error::DeviceError("Virtual deck has no touch strip".to_string()))?;
+        imageops::replace(lcd, &image, x as i64, y as i64);
+        Ok(())
+    }
+
+    fn reader(&self) -> Arc<dyn DeckReader> {
+        self.reader.clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl DeckReader for VirtualReader {
+    async fn read(&self, _poll_rate: f32) -> Result<Vec<DeviceStateUpdate>> {
+        let mut events = self.events.lock().await;
+        let first = events
+            .recv()
+            .await
+            .ok_or_else(|| Error::DeviceError("Virtual deck was dropped".to_string()))?;
+        let mut updates = vec![first];
+        while let Ok(event) = events.try_recv() {
+            updates.push(event);
+        }
+        Ok(updates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        button::{render_button, Icon, RenderConfig},
+        input::InputConfig,
+        navigation::NavigationEntry,
+        run::run_with_input_config,
+        view::{
+            customizable::{CustomizableView, ToggleButton},
+            Button, ButtonState, View,
+        },
+        Theme,
+    };
+    use generic_array::typenum::{U3, U5};
+    use std::{sync::atomic::AtomicBool, time::Duration};
+
+    /// Whether the toggle of the test view is active.
+    type Muted = Arc<AtomicBool>;
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct Home;
+
+    impl NavigationEntry<U5, U3, Muted> for Home {
+        async fn get_view(
+            &self,
+            _context: Muted,
+        ) -> std::result::Result<Box<dyn View<U5, U3, Muted, Home>>, Box<dyn std::error::Error>>
+        {
+            let mut view = CustomizableView::new();
+            view.set_button(
+                0,
+                0,
+                ToggleButton::new(
+                    "Mute",
+                    None::<Icon>,
+                    |muted: Muted| async move { Ok(muted.load(Ordering::SeqCst)) },
+                    |muted: Muted, value| async move {
+                        muted.store(value, Ordering::SeqCst);
+                        Ok(())
+                    },
+                ),
+            )?;
+            Ok(Box::new(view))
+        }
+    }
+
+    /// Render a button like the display manager does with the defaults.
+    fn rendered(button: Button) -> DynamicImage {
+        let config = RenderConfig::default();
+        render_button(
+            &button.to_render_button(&Theme::default()),
+            &button.render_config(&config),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn click_re_renders_key() {
+        let deck = Arc::new(VirtualDeck::new(15));
+        let muted = Muted::default();
+        let run = run_with_input_config::<Home, U5, U3, Muted>(
+            Theme::default(),
+            RenderConfig::default(),
+            InputConfig::default(),
+            deck.clone(),
+            muted.clone(),
+            None,
+        );
+        let check = async {
+            deck.wait_for_flushes(1).await;
+            let idle = rendered(Button::with_state("Mute".to_string(), ButtonState::Default));
+            assert!(deck.key_image(0).unwrap() == idle);
+
+            deck.click(0);
+            let active = rendered(Button::with_state("Mute".to_string(), ButtonState::Active));
+            loop {
+                let flushes = deck.flush_count();
+                if deck.last_frame().and_then(|frame| frame.key(0).cloned()) == Some(active.clone())
+                {
+                    break;
+                }
+                deck.wait_for_flushes(flushes + 1).await;
+            }
+        };
+
+        let result = tokio::time::timeout(Duration::from_secs(5), async {
+            tokio::select! {
+                result = run => panic!("the run loop stopped: {:?}", result.err()),
+                _ = check => {}
+            }
+        })
+        .await;
+        assert!(result.is_ok(), "the key was not re-rendered as active");
+        assert!(muted.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn flush_records_frames() {
+        let deck = VirtualDeck::new(2);
+        let image = DynamicImage::new_rgba8(4, 4);
+        deck.set_button_image(1, image.clone()).await.unwrap();
+        assert!(deck.set_button_image(2, image.clone()).await.is_err());
+        assert!(deck.frames().is_empty());
+
+        deck.flush().await.unwrap();
+        deck.flush().await.unwrap();
+        assert_eq!(deck.flush_count(), 2);
+        let frame = deck.last_frame().unwrap();
+        assert!(frame.key(0).is_none());
+        assert!(frame.key(1) == Some(&image));
+    }
+
+    #[tokio::test]
+    async fn reader_returns_injected_events() {
+        let deck = VirtualDeck::new(15);
+        deck.click(3);
+        deck.twist(1, -2);
+        let events = deck.reader().read(0.0).await.unwrap();
+        let events: Vec<String> = events.iter().map(|event| format!("{:?}", event)).collect();
+        assert_eq!(events, ["ButtonDown(3)", "ButtonUp(3)", "EncoderTwist(1, -2)"]);
+    }
+}
@@ -0,0 +1,607 @@
+//! Web Stream Deck simulator and mirror.
+//!
+//! This module provides a device that serves the key images over HTTP on
+//! a local address and accepts clicks from the browser over a WebSocket.
+//! It can run on its own as a simulator, or wrap another device as a live
+//! mirror. It is only available with the `web` feature.
+//!
+//! WebSocket connections from browsers are only accepted from the page
+//! served by the simulator itself, so that other web pages open in the
+//! browser cannot press keys.
+
+use std::{
+    io::Cursor,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, State,
+    },
+    http::{header, uri::Authority, HeaderMap, StatusCode, Uri},
+    response::{Html, IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use elgato_streamdeck::DeviceStateUpdate;
+use image::{DynamicImage, ImageFormat};
+use tokio::{
+    net::{TcpListener, ToSocketAddrs},
+    sync::{mpsc, watch},
+    task::JoinHandle,
+};
+
+use crate::error::{Error, Result};
+
+use super::{DeckDevice, DeckReader};
+
+/// State shared between the device and the HTTP server.
+struct WebState {
+    /// The number of key columns.
+    columns: u8,
+    /// The number of key rows.
+    rows: u8,
+    /// The address the server listens on.
+    address: SocketAddr,
+    /// PNG images uploaded since the last flush.
+    pending: Mutex<Vec<Option<Arc<Vec<u8>>>>>,
+    /// PNG images served to the browser.
+    published: Mutex<Vec<Option<Arc<Vec<u8>>>>>,
+    /// The number of flushes so far, watched by connected browsers.
+    version: watch::Sender<u64>,
+    /// The sender for clicks from the browser.
+    events: mpsc::UnboundedSender<DeviceStateUpdate>,
+}
+
+/// A Stream Deck simulator served over HTTP.
+///
+/// This struct implements `DeckDevice` by publishing every flushed key
+/// image as a PNG on a local web page. Keys clicked in the browser are
+/// reported as button presses. When created with `mirror`, all calls are
+/// also forwarded to another device and input from both is merged.
+pub struct WebDeck {
+    /// State shared with the HTTP server.
+    state: Arc<WebState>,
+    /// The mirrored device.
+    inner: Option<Arc<dyn DeckDevice>>,
+    /// The reader handed out to the run loop.
+    reader: Arc<WebReader>,
+    /// The address the server listens on.
+    address: SocketAddr,
+    /// The HTTP server task.
+    server: JoinHandle<()>,
+}
+
+/// The reader of a web simulator.
+pub struct WebReader {
+    /// The receiver for clicks from the browser.
+    events: tokio::sync::Mutex<mpsc::UnboundedReceiver<DeviceStateUpdate>>,
+    /// The reader of the mirrored device.
+    inner: Option<Arc<dyn DeckReader>>,
+}
+
+impl WebDeck {
+    /// Start a web simulator with the given number of key columns and rows.
+    ///
+    /// The page is served on the given address, for example
+    /// `"127.0.0.1:8080"`.
+    pub async fn bind(address: impl ToSocketAddrs, columns: u8, rows: u8) -> Result<Self> {
+        WebDeck::start(address, columns, rows, None).await
+    }
+
+    /// Start a web mirror of another device.
+    ///
+    /// Everything sent to the returned device is also sent to `inner`,
+    /// and input from `inner` is reported next to clicks from the browser.
+    pub async fn mirror(
+        address: impl ToSocketAddrs,
+        columns: u8,
+        rows: u8,
+        inner: Arc<dyn DeckDevice>,
+    ) -> Result<Self> {
+        WebDeck::start(address, columns, rows, Some(inner)).await
+    }
+
+    async fn start(
+        address: impl ToSocketAddrs,
+        columns: u8,
+        rows: u8,
+        inner: Option<Arc<dyn DeckDevice>>,
+    ) -> Result<Self> {
+        let listener = TcpListener::bind(address).await?;
+        let address = listener.local_addr()?;
+        let key_count = columns as usize * rows as usize;
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (version, _) = watch::channel(0);
+        let state = Arc::new(WebState {
+            columns,
+            rows,
+            address,
+            pending: Mutex::new(vec![None; key_count]),
+            published: Mutex::new(vec![None; key_count]),
+            version,
+            events: sender,
+        });
+
+        let app = Router::new()
+            .route("/", get(index_page))
+            .route("/keys/{index}", get(key_image))
+            .route("/ws", get(websocket))
+            .with_state(state.clone());
+        let server = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                eprintln!("Web simulator stopped: {}", e);
+            }
+        });
+
+        Ok(WebDeck {
+            state,
+            reader: Arc::new(WebReader {
+                events: tokio::sync::Mutex::new(receiver),
+                inner: inner.as_ref().map(|inner| inner.reader()),
+            }),
+            inner,
+            address,
+            server,
+        })
+    }
+
+    /// Get the address the page is served on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+}
+
+impl Drop for WebDeck {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+#[async_trait::async_trait]
+impl DeckDevice for WebDeck {
+    async fn set_button_image(&self, index: u8, image: DynamicImage) -> Result<()> {
+        let mut png = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .map_err(|e| Error::ImageError(e.to_string()))?;
+        if let Some(inner) = &self.inner {
+            inner.set_button_image(index, image).await?;
+        }
+        let mut pending = self.state.pending.lock().unwrap();
+        match pending.get_mut(index as usize) {
+            Some(key) => *key = Some(Arc::new(png)),
+            // Keys that do not fit on the page are only shown on the
+            // mirrored device.
+            None if self.inner.is_some() => {}
+            None => return Err(Error::ButtonIndexOutOfBounds(index as usize)),
+        }
+        Ok(())
+    }
+
+    async fn set_button_info(&self, index: u8, button: &crate::view::Button) -> Result<()> {
+        if let Some(inner) = &self.inner {
+            inner.set_button_info(index, button).await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&self) -> Result<()> {
+        let pending = self.state.pending.lock().unwrap().clone();
+        *self.state.published.lock().unwrap() = pending;
+        self.state.version.send_modify(|version| *version += 1);
+        if let Some(inner) = &self.inner {
+            inner.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn set_brightness(&self, percent: u8) -> Result<()> {
+        if let Some(inner) = &self.inner {
+            inner.set_brightness(percent).await?;
+        }
+        Ok(())
+    }
+
+    fn lcd_size(&self) -> Option<(u32, u32)> {
+        self.inner.as_ref().and_then(|inner| inner.lcd_size())
+    }
+
+    fn encoder_count(&self) -> u8 {
+        self.inner
+            .as_ref()
+            .map(|inner| inner.encoder_count())
+            .unwrap_or(0)
+    }
+
+    async fn set_lcd_image(&self, x: u16, y: u16, image: DynamicImage) -> Result<()> {
+        if let Some(inner) = &self.inner {
+            inner.set_lcd_image(x, y, image).await?;
+        }
+        Ok(())
+    }
+
+    fn reader(&self) -> Arc<dyn DeckReader> {
+        self.reader.clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl DeckReader for WebReader {
+    async fn read(&self, poll_rate: f32) -> Result<Vec<DeviceStateUpdate>> {
+        let mut events = self.events.lock().await;
+        let first = match &self.inner {
+            Some(inner) => tokio::select! {
+                updates = inner.read(poll_rate) => return updates,
+                event = events.recv() => event,
+            },
+            None => events.recv().await,
+        };
+        let first =
+            first.ok_or_else(|| Error::DeviceError("Web simulator was dropped".to_string()))?;
+        let mut updates = vec![first];
+        while let Ok(event) = events.try_recv() {
+            updates.push(event);
+        }
+        Ok(updates)
+    }
+}
+
+/// Serve the simulator page.
+async fn index_page(State(state): State<Arc<WebState>>) -> Html<String> {
+    Html(
+        INDEX_PAGE
+            .replace("{columns}", &state.columns.to_string())
+            .replace("{rows}", &state.rows.to_string()),
+    )
+}
+
+/// Serve the PNG image of a single key.
+async fn key_image(State(state): State<Arc<WebState>>, Path(index): Path<usize>) -> Response {
+    let published = state.published.lock().unwrap();
+    match published.get(index).cloned().flatten() {
+        Some(png) => (
+            [
+                (header::CONTENT_TYPE, "image/png"),
+                (header::CACHE_CONTROL, "no-store"),
+            ],
+            png.as_ref().clone(),
+        )
+            .into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// Upgrade a browser connection to a WebSocket.
+///
+/// Connections opened by other web pages are refused with `403 Forbidden`.
+async fn websocket(
+    State(state): State<Arc<WebState>>,
+    headers: HeaderMap,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    if !origin_allowed(&headers, state.address) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    upgrade.on_upgrade(move |socket| handle_socket(state, socket))
+}
+
+/// Check whether a WebSocket connection comes from the simulator page.
+///
+/// Browsers always send an `Origin` header, which must name the same host
+/// and port as the `Host` header. The host must be `localhost` or an IP
+/// address the server is bound to, so that a foreign domain resolving to
+/// the local machine is refused as well. Clients that are not browsers
+/// send no `Origin` and are accepted.
+fn origin_allowed(headers: &HeaderMap, address: SocketAddr) -> bool {
+    let Some(origin) = headers.get(header::ORIGIN) else {
+        return true;
+    };
+    let header_authority = |name| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<Authority>().ok())
+    };
+    let Some(host) = header_authority(header::HOST) else {
+        return false;
+    };
+    let Some(origin) = origin
+        .to_str()
+        .ok()
+        .and_then(|origin| origin.parse::<Uri>().ok())
+    else {
+        return false;
+    };
+    if origin.scheme_str() != Some("http")
+        || origin.authority().map(|authority| authority.as_str().to_ascii_lowercase())
+            != Some(host.as_str().to_ascii_lowercase())
+        || host.port_u16() != Some(address.port())
+    {
+        return false;
+    }
+    let name = host.host().trim_start_matches('[').trim_end_matches(']');
+    if name.eq_ignore_ascii_case("localhost") {
+        return address.ip().is_loopback() || address.ip().is_unspecified();
+    }
+    match name.parse::<IpAddr>() {
+        Ok(ip) => address.ip().is_unspecified() || ip == address.ip(),
+        Err(_) => false,
+    }
+}
+
+/// Send frame versions to the browser and receive clicks from it.
+///
+/// The browser reloads the key images whenever it receives a new version,
+/// and sends `down <index>` and `up <index>` messages for clicks.
+async fn handle_socket(state: Arc<WebState>, mut socket: WebSocket) {
+    let mut version = state.version.subscribe();
+    let current = *version.borrow_and_update();
+    if socket
+        .send(Message::Text(current.to_string().into()))
+        .await
+        .is_err()
+    {
+        return;
+    }
+    loop {
+        tokio::select! {
+            changed = version.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+                let current = *version.borrow_and_update();
+                if socket.send(Message::Text(current.to_string().into())).await.is_err() {
+                    return;
+                }
+            }
+            message = socket.recv() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(_)) => continue,
+                    _ => return,
+                };
+                let event = match text.split_once(' ') {
+                    Some(("down", index)) => index.parse().ok().map(DeviceStateUpdate::ButtonDown),
+                    Some(("up", index)) => index.parse().ok().map(DeviceStateUpdate::ButtonUp),
+                    _ => None,
+                };
+                if let Some(event) = event {
+                    let _ = state.events.send(event);
+                }
+            }
+        }
+    }
+}
+
+/// The simulator page.
+const INDEX_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Stream Deck</title>
+<style>
+  body { background: #111; color: #aaa; font-family: sans-serif; display: flex;
+         flex-direction: column; align-items: center; }
+  #deck { display: grid; grid-template-columns: repeat({columns}, 96px); gap: 16px;
+          padding: 24px; background: #000; border-radius: 16px; }
+  #deck img { width: 96px; height: 96px; border-radius: 12px; background: #222;
+              cursor: pointer; user-select: none; -webkit-user-drag: none; }
+</style>
+</head>
+<body>
+<h1>Stream Deck</h1>
+<div id="deck"></div>
+<p id="status">Connecting...</p>
+<script>
+  const deck = document.getElementById("deck");
+  const status = document.getElementById("status");
+  const keys = [];
+  let socket = null;
+  for (let i = 0; i < {columns} * {rows}; i++) {
+    const key = document.createElement("img");
+    key.alt = "";
+    key.addEventListener("pointerdown", (e) => {
+      key.setPointerCapture(e.pointerId);
+      if (socket) socket.send("down " + i);
+    });
+    key.addEventListener("pointerup", () => { if (socket) socket.send("up " + i); });
+    deck.appendChild(key);
+    keys.push(key);
+  }
+  function connect() {
+    const ws = new WebSocket("ws://" + location.host + "/ws");
+    ws.onopen = () => { socket = ws; status.textContent = "Connected"; };
+    ws.onmessage = (message) => {
+      keys.forEach((key, i) => { key.src = "/keys/" + i + "?v=" + message.data; });
+    };
+    ws.onclose = () => {
+      socket = null;
+      status.textContent = "Disconnected, retrying...";
+      setTimeout(connect, 1000);
+    };
+  }
+  connect();
+</script>
+</body>
+</html>
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::VirtualDeck;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Send a WebSocket handshake and return the status line of the reply.
+    async fn upgrade(address: SocketAddr, host: &str, origin: Option<&str>) -> String {
+        let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+        let origin = origin
+            .map(|origin| format!("Origin: {}\r\n", origin))
+            .unwrap_or_default();
+        let request = format!(
+            "GET /ws HTTP/1.1\r\nHost: {}\r\n{}Connection: Upgrade\r\nUpgrade: websocket\r\n\
+             Sec-WebSocket-Version: 13\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+            host, origin
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut reply = vec![0; 1024];
+        let read = stream.read(&mut reply).await.unwrap();
+        let reply = String::from_utf8_lossy(&reply[..read]).to_string();
+        reply.lines().next().unwrap_or_default().to_string()
+    }
+
+    #[tokio::test]
+    async fn refuses_foreign_origin() {
+        let deck = WebDeck::bind("127.0.0.1:0", 5, 3).await.unwrap();
+        let address = deck.local_addr();
+        let host = address.to_string();
+        let own = format!("http://{}", host);
+
+        let status = upgrade(address, &host, Some("http://evil.example")).await;
+        assert!(status.contains("403"), "{}", status);
+        let rebound = format!("evil.example:{}", address.port());
+        let status = upgrade(address, &rebound, Some(&format!("http://{}", rebound))).await;
+        assert!(status.contains("403"), "{}", status);
+
+        let status = upgrade(address, &host, Some(&own)).await;
+        assert!(status.contains("101"), "{}", status);
+        let status = upgrade(address, &host, None).await;
+        assert!(status.contains("101"), "{}", status);
+    }
+
+    /// Open a WebSocket connection and read the first frame version.
+    async fn connect(address: SocketAddr) -> tokio::net::TcpStream {
+        let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+        let request = format!(
+            "GET /ws HTTP/1.1\r\nHost: {}\r\nConnection: Upgrade\r\nUpgrade: websocket\r\n\
+             Sec-WebSocket-Version: 13\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+            address
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut reply = Vec::new();
+        while !reply.ends_with(b"\r\n\r\n") {
+            reply.push(stream.read_u8().await.unwrap());
+        }
+        assert!(reply.starts_with(b"HTTP/1.1 101"));
+        read_text(&mut stream).await;
+        stream
+    }
+
+    /// Read a short text frame sent by the server.
+    async fn read_text(stream: &mut tokio::net::TcpStream) -> String {
+        assert_eq!(stream.read_u8().await.unwrap(), 0x81);
+        let mut payload = vec![0; stream.read_u8().await.unwrap() as usize];
+        stream.read_exact(&mut payload).await.unwrap();
+        String::from_utf8(payload).unwrap()
+    }
+
+    /// Send a short text frame, masked like browsers do.
+    async fn send_text(stream: &mut tokio::net::TcpStream, text: &str) {
+        let mask = [0x12, 0x34, 0x56, 0x78];
+        let mut frame = vec![0x81, 0x80 | text.len() as u8];
+        frame.extend_from_slice(&mask);
+        frame.extend(text.bytes().enumerate().map(|(i, byte)| byte ^ mask[i % 4]));
+        stream.write_all(&frame).await.unwrap();
+    }
+
+    /// Fetch a key image, returning the status code and the body.
+    async fn get_key(address: SocketAddr, index: u8) -> (u16, Vec<u8>) {
+        let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+        let request = format!(
+            "GET /keys/{} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+            index, address
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut reply = Vec::new();
+        stream.read_to_end(&mut reply).await.unwrap();
+        let split = reply.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+        let status = String::from_utf8_lossy(&reply[9..12]).parse().unwrap();
+        (status, reply[split + 4..].to_vec())
+    }
+
+    #[tokio::test]
+    async fn clicks_are_read_from_the_socket() {
+        let deck = WebDeck::bind("127.0.0.1:0", 5, 3).await.unwrap();
+        let mut socket = connect(deck.local_addr()).await;
+        send_text(&mut socket, "down 0").await;
+        send_text(&mut socket, "up 0").await;
+
+        let reader = deck.reader();
+        let mut events = Vec::new();
+        while events.len() < 2 {
+            let updates = tokio::time::timeout(Duration::from_secs(5), reader.read(0.0))
+                .await
+                .unwrap()
+                .unwrap();
+            events.extend(updates.iter().map(|update| format!("{:?}", update)));
+        }
+        assert_eq!(events, ["ButtonDown(0)", "ButtonUp(0)"]);
+    }
+
+    #[tokio::test]
+    async fn key_images_change_on_flush() {
+        let deck = WebDeck::bind("127.0.0.1:0", 5, 3).await.unwrap();
+        let address = deck.local_addr();
+        let mut socket = connect(address).await;
+        let image = |color| DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(8, 8, color));
+
+        assert_eq!(get_key(address, 0).await.0, 404);
+        deck.set_button_image(0, image(image::Rgba([255, 0, 0, 255])))
+            .await
+            .unwrap();
+        assert_eq!(get_key(address, 0).await.0, 404);
+
+        deck.flush().await.unwrap();
+        assert_eq!(read_text(&mut socket).await, "1");
+        let (status, red) = get_key(address, 0).await;
+        assert_eq!(status, 200);
+        let decoded = image::load_from_memory(&red).unwrap().to_rgba8();
+        assert_eq!(decoded.get_pixel(0, 0).0, [255, 0, 0, 255]);
+
+        deck.set_button_image(0, image(image::Rgba([0, 0, 255, 255])))
+            .await
+            .unwrap();
+        assert_eq!(get_key(address, 0).await.1, red);
+        deck.flush().await.unwrap();
+        assert_eq!(read_text(&mut socket).await, "2");
+        assert_ne!(get_key(address, 0).await.1, red);
+    }
+
+    #[tokio::test]
+    async fn mirror_forwards_keys_beyond_the_page() {
+        let inner = Arc::new(VirtualDeck::new(15));
+        let deck = WebDeck::mirror("127.0.0.1:0", 2, 1, inner.clone())
+            .await
+            .unwrap();
+
+        deck.set_button_image(5, DynamicImage::new_rgba8(8, 8))
+            .await
+            .unwrap();
+        assert!(inner.key_image(5).is_some());
+    }
+
+    #[test]
+    fn origin_must_match_bound_host_and_port() {
+        let headers = |host: &str, origin: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::HOST, host.parse().unwrap());
+            headers.insert(header::ORIGIN, origin.parse().unwrap());
+            headers
+        };
+        let loopback: SocketAddr = "127.0.0.1:8080".parse().unwrap();
+        let any: SocketAddr = "0.0.0.0:8080".parse().unwrap();
+
+        assert!(origin_allowed(&headers("127.0.0.1:8080", "http://127.0.0.1:8080"), loopback));
+        assert!(origin_allowed(&headers("localhost:8080", "http://localhost:8080"), loopback));
+        assert!(origin_allowed(&headers("192.168.1.2:8080", "http://192.168.1.2:8080"), any));
+        assert!(!origin_allowed(&headers("192.168.1.2:8080", "http://192.168.1.2:8080"), loopback));
+        assert!(!origin_allowed(&headers("127.0.0.1:8080", "http://127.0.0.1:9090"), loopback));
+        assert!(!origin_allowed(&headers("127.0.0.1:9090", "http://127.0.0.1:9090"), loopback));
+        assert!(!origin_allowed(&headers("127.0.0.1:8080", "https://127.0.0.1:8080"), loopback));
+        assert!(!origin_allowed(&headers("127.0.0.1:8080", "null"), loopback));
+    }
+}
@@ -0,0 +1,151 @@
+//! Input configuration for Stream Deck applications.
+//!
+//! This module provides types for configuring how button presses are
+//! turned into clicks and other gestures.
+
+use std::time::Duration;
+
+/// Configuration for input handling.
+///
+/// This struct contains the timings used to recognize gestures
+/// from button presses and releases.
+#[derive(Clone, Copy, Debug)]
+pub struct InputConfig {
+    /// How long a button has to be held to count as a long press.
+    pub(crate) long_press: Duration,
+    /// How long to wait for the next tap of a multi-tap gesture.
+    pub(crate) multi_tap_window: Duration,
+    /// How long a repeating button has to be held before it repeats.
+    pub(crate) repeat_delay: Duration,
+    /// How often a repeating button repeats while it is held.
+    pub(crate) repeat_interval: Duration,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        InputConfig {
+            long_press: Duration::from_millis(500),
+            multi_tap_window: Duration::from_millis(300),
+            repeat_delay: Duration::from_millis(400),
+            repeat_interval: Duration::from_millis(100),
+        }
+    }
+}
+
+impl InputConfig {
+    /// Create a new input config with default timings.
+    pub fn new() -> Self {
+        InputConfig::default()
+    }
+
+    /// Set how long a button has to be held to count as a long press.
+    pub fn with_long_press(self, long_press: Duration) -> Self {
+        InputConfig { long_press, ..self }
+    }
+
+    /// Set how long to wait for the next tap of a multi-tap gesture.
+    ///
+    /// Clicks on buttons that handle multi-tap gestures are delayed by
+    /// up to this duration.
+    pub fn with_multi_tap_window(self, multi_tap_window: Duration) -> Self {
+        InputConfig {
+            multi_tap_window,
+            ..self
+        }
+    }
+
+    /// Set how long a repeating button has to be held before it repeats.
+    pub fn with_repeat_delay(self, repeat_delay: Duration) -> Self {
+        InputConfig {
+            repeat_delay,
+            ..self
+        }
+    }
+
+    /// Set how often a repeating button repeats while it is held.
+    pub fn with_repeat_interval(self, repeat_interval: Duration) -> Self {
+        InputConfig {
+            repeat_interval,
+            ..self
+        }
+    }
+}
+
+/// A gesture made of several quick taps on the same button.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MultiTap {
+    /// Two taps.
+    Double,
+    /// Three taps.
+    Triple,
+}
+
+impl MultiTap {
+    /// Get the number of taps in the gesture.
+    pub fn count(&self) -> u8 {
+        match self {
+            MultiTap::Double => 2,
+            MultiTap::Triple => 3,
+        }
+    }
+
+    /// Get the gesture for the given number of taps.
+    pub fn from_count(count: u8) -> Option<Self> {
+        match count {
+            2 => Some(MultiTap::Double),
+            3 => Some(MultiTap::Triple),
+            _ => None,
+        }
+    }
+}
+
+/// A gesture on the touch strip.
+///
+/// Positions are in pixels, relative to the top-left corner of the strip.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TouchEvent {
+    /// The strip was touched briefly.
+    Short {
+        /// The horizontal position.
+        x: u16,
+        /// The vertical position.
+        y: u16,
+    },
+    /// The strip was touched and held.
+    Long {
+        /// The horizontal position.
+        x: u16,
+        /// The vertical position.
+        y: u16,
+    },
+    /// A finger was swiped across the strip.
+    Swipe {
+        /// The position where the swipe started.
+        from: (u16, u16),
+        /// The position where the swipe ended.
+        to: (u16, u16),
+    },
+}
+
+impl TouchEvent {
+    /// Get the position where the gesture started.
+    pub fn position(&self) -> (u16, u16) {
+        match self {
+            TouchEvent::Short { x, y } | TouchEvent::Long { x, y } => (*x, *y),
+            TouchEvent::Swipe { from, .. } => *from,
+        }
+    }
+
+    /// Get the index of the strip segment where the gesture started.
+    ///
+    /// The strip is divided into the given number of segments of equal
+    /// width, one above each encoder.
+    pub fn segment(&self, strip_width: u32, segments: u8) -> Option<u8> {
+        if strip_width == 0 || segments == 0 {
+            return None;
+        }
+        let (x, _) = self.position();
+        let segment = x as u32 * segments as u32 / strip_width;
+        (segment < segments as u32).then_some(segment as u8)
+    }
+}
@@ -8,29 +8,41 @@
 
 // Re-export modules
 pub mod button;
+pub mod device;
 pub mod error;
+pub mod input;
 pub mod navigation;
+pub mod screenshot;
 pub mod theme;
 pub mod view;
 
 // Re-export commonly used items
-pub use button::RenderConfig;
+pub use button::{
+    FontChain, FontWeight, Icon, ImageFit, Layout, RasterImage, RenderCache, RenderConfig,
+    TextPosition,
+};
+pub use device::{DeckDevice, DeckReader};
 pub use elgato_streamdeck;
 pub use generic_array;
+pub use input::{InputConfig, MultiTap, TouchEvent};
 pub use md_icons;
 pub use navigation::NavigationEntry;
 pub use theme::Theme;
-pub use view::{Button, ButtonState, DisplayManager, View};
+pub use view::{Button, ButtonState, DisplayManager, Strip, View};
 
 // Optional plugins module
 #[cfg(feature = "plugins")]
 pub mod plugins;
 
+// Optional test support module
+#[cfg(feature = "testing")]
+pub mod testing;
+
 /// Run a Stream Deck application with the specified configuration.
 ///
-/// This function takes a theme, render configuration, Stream Deck instance,
+/// This function takes a theme, render configuration, device,
 /// and application context, and runs the main event loop.
-pub use crate::run::{run, run_with_external_triggers, ExternalTrigger};
+pub use crate::run::{run, run_with_external_triggers, run_with_input_config, ExternalTrigger};
 
 // Internal modules
 mod run;
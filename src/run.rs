@@ -1,19 +1,25 @@
 use std::{marker::PhantomData, sync::Arc};
 
-use elgato_streamdeck::AsyncStreamDeck;
+use elgato_streamdeck::DeviceStateUpdate;
+use tokio::sync::mpsc;
 
 use crate::{
-    button::RenderConfig, navigation::NavigationEntry, theme::Theme, view::DisplayManager,
+    button::RenderConfig,
+    device::DeckDevice,
+    input::{InputConfig, TouchEvent},
+    navigation::NavigationEntry,
+    theme::Theme,
+    view::DisplayManager,
 };
 
 /// Run a Stream Deck application with the specified configuration.
 ///
-/// This function takes a theme, render configuration, Stream Deck instance,
+/// This function takes a theme, render configuration, device,
 /// and application context, and runs the main event loop.
 pub async fn run<N, W, H, C>(
     theme: Theme,
     config: RenderConfig,
-    deck: Arc<AsyncStreamDeck>,
+    deck: Arc<impl DeckDevice>,
     context: C,
 ) -> Result<(), Box<dyn std::error::Error>>
 where
@@ -22,38 +28,15 @@ where
     C: Send + Sync + Clone + 'static,
     N: NavigationEntry<W, H, C>,
 {
-    let (display_manager, mut navigation_receiver) =
-        DisplayManager::<N, W, H, C>::new(deck.clone(), config, theme, context).await?;
-
-    display_manager.fetch_all().await?;
-    display_manager.render().await?;
-
-    let reader = deck.get_reader();
-    loop {
-        let events_future = reader.read(10.0);
-        let navigation_future = navigation_receiver.recv();
-        tokio::select! {
-            events = events_future => {
-                let events = events?;
-                for event in events {
-                    match event {
-                        elgato_streamdeck::DeviceStateUpdate::ButtonDown(id) => {
-                            display_manager.on_press(id).await?;
-                        }
-                        elgato_streamdeck::DeviceStateUpdate::ButtonUp(id) => {
-                            display_manager.on_release(id).await?;
-                        }
-                        _ => {}
-                    }
-                }
-            }
-            Some(navigation) = navigation_future => {
-                display_manager.navigate_to(navigation).await?;
-                display_manager.fetch_all().await?;
-                display_manager.render().await?;
-            }
-        }
-    }
+    run_with_input_config::<N, W, H, C>(
+        theme,
+        config,
+        InputConfig::default(),
+        deck,
+        context,
+        None,
+    )
+    .await
 }
 
 pub struct ExternalTrigger<N, W, H, C> {
@@ -82,9 +65,40 @@ impl<N, W, H, C> ExternalTrigger<N, W, H, C> {
 pub async fn run_with_external_triggers<N, W, H, C>(
     theme: Theme,
     config: RenderConfig,
-    deck: Arc<AsyncStreamDeck>,
+    deck: Arc<impl DeckDevice>,
+    context: C,
+    receiver: mpsc::Receiver<ExternalTrigger<N, W, H, C>>,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    W: generic_array::ArrayLength,
+    H: generic_array::ArrayLength,
+    C: Send + Sync + Clone + 'static,
+    N: NavigationEntry<W, H, C>,
+{
+    run_with_input_config::<N, W, H, C>(
+        theme,
+        config,
+        InputConfig::default(),
+        deck,
+        context,
+        Some(receiver),
+    )
+    .await
+}
+
+/// Run a Stream Deck application with the specified configuration, input
+/// configuration and optional external triggers.
+///
+/// This function takes a theme, render configuration, input configuration,
+/// device, application context, and external triggers, and runs the main
+/// event loop.
+pub async fn run_with_input_config<N, W, H, C>(
+    theme: Theme,
+    config: RenderConfig,
+    input: InputConfig,
+    deck: Arc<impl DeckDevice>,
     context: C,
-    mut receiver: tokio::sync::mpsc::Receiver<ExternalTrigger<N, W, H, C>>,
+    mut receiver: Option<mpsc::Receiver<ExternalTrigger<N, W, H, C>>>,
 ) -> Result<(), Box<dyn std::error::Error>>
 where
     W: generic_array::ArrayLength,
@@ -94,26 +108,57 @@ where
 {
     let (display_manager, mut navigation_receiver) =
         DisplayManager::<N, W, H, C>::new(deck.clone(), config, theme, context).await?;
+    let display_manager = display_manager.with_input_config(input);
 
     display_manager.fetch_all().await?;
     display_manager.render().await?;
 
-    let reader = deck.get_reader();
+    let reader = deck.reader();
     loop {
         let events_future = reader.read(10.0);
         let navigation_future = navigation_receiver.recv();
-        let trigger_future = receiver.recv();
+        let trigger_future = async {
+            match receiver.as_mut() {
+                Some(receiver) => receiver.recv().await,
+                None => std::future::pending().await,
+            }
+        };
+        let deadline = display_manager.next_deadline();
+        let timer_future = async {
+            match deadline {
+                Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+                None => std::future::pending().await,
+            }
+        };
         tokio::select! {
             events = events_future => {
                 let events = events?;
                 for event in events {
                     match event {
-                        elgato_streamdeck::DeviceStateUpdate::ButtonDown(id) => {
+                        DeviceStateUpdate::ButtonDown(id) => {
                             display_manager.on_press(id).await?;
                         }
-                        elgato_streamdeck::DeviceStateUpdate::ButtonUp(id) => {
+                        DeviceStateUpdate::ButtonUp(id) => {
                             display_manager.on_release(id).await?;
                         }
+                        DeviceStateUpdate::EncoderTwist(id, ticks) => {
+                            display_manager.on_encoder_twist(id, ticks).await?;
+                        }
+                        DeviceStateUpdate::EncoderDown(id) => {
+                            display_manager.on_encoder_press(id).await?;
+                        }
+                        DeviceStateUpdate::EncoderUp(id) => {
+                            display_manager.on_encoder_release(id).await?;
+                        }
+                        DeviceStateUpdate::TouchScreenPress(x, y) => {
+                            display_manager.on_touch(TouchEvent::Short { x, y }).await?;
+                        }
+                        DeviceStateUpdate::TouchScreenLongPress(x, y) => {
+                            display_manager.on_touch(TouchEvent::Long { x, y }).await?;
+                        }
+                        DeviceStateUpdate::TouchScreenSwipe(from, to) => {
+                            display_manager.on_touch(TouchEvent::Swipe { from, to }).await?;
+                        }
                         _ => {}
                     }
                 }
@@ -130,6 +175,177 @@ where
                     display_manager.render().await?;
                 }
             }
+            _ = timer_future => {
+                display_manager.on_tick().await?;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        button::Icon,
+        device::VirtualDeck,
+        view::{
+            customizable::{CustomizableView, DialValue},
+            ButtonMatrix, View,
+        },
+    };
+    use generic_array::typenum::{U3, U5};
+    use std::{sync::Mutex, time::Duration};
+
+    type Log = Arc<Mutex<Vec<String>>>;
+
+    /// A view that records the encoder events it receives.
+    struct Recorder;
+
+    #[async_trait::async_trait]
+    impl View<U5, U3, Log, Recorded> for Recorder {
+        async fn render(&self) -> Result<ButtonMatrix<U5, U3>, Box<dyn std::error::Error>> {
+            Ok(ButtonMatrix::new())
+        }
+
+        async fn on_click(
+            &self,
+            _context: &Log,
+            _index: u8,
+            _navigation: Arc<mpsc::Sender<Recorded>>,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            Ok(())
+        }
+
+        async fn on_encoder_twist(
+            &self,
+            log: &Log,
+            encoder: u8,
+            ticks: i8,
+            _navigation: Arc<mpsc::Sender<Recorded>>,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            log.lock()
+                .unwrap()
+                .push(format!("twist {} {}", encoder, ticks));
+            Ok(())
+        }
+
+        async fn on_encoder_press(
+            &self,
+            log: &Log,
+            encoder: u8,
+            _navigation: Arc<mpsc::Sender<Recorded>>,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            log.lock().unwrap().push(format!("press {}", encoder));
+            Ok(())
+        }
+
+        async fn on_encoder_release(
+            &self,
+            log: &Log,
+            encoder: u8,
+            _navigation: Arc<mpsc::Sender<Recorded>>,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            log.lock().unwrap().push(format!("release {}", encoder));
+            Ok(())
         }
+
+        async fn fetch_all(&self, _context: &Log) -> Result<(), Box<dyn std::error::Error>> {
+            Ok(())
+        }
+    }
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct Recorded;
+
+    impl NavigationEntry<U5, U3, Log> for Recorded {
+        async fn get_view(
+            &self,
+            _context: Log,
+        ) -> Result<Box<dyn View<U5, U3, Log, Recorded>>, Box<dyn std::error::Error>> {
+            Ok(Box::new(Recorder))
+        }
+    }
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct Dial;
+
+    impl NavigationEntry<U5, U3, Log> for Dial {
+        async fn get_view(
+            &self,
+            _context: Log,
+        ) -> Result<Box<dyn View<U5, U3, Log, Dial>>, Box<dyn std::error::Error>> {
+            let mut view = CustomizableView::new();
+            view.set_encoder(
+                0,
+                DialValue::new(
+                    "Volume",
+                    None::<Icon>,
+                    |_: Log| async { Ok(50) },
+                    |log: Log, value| async move {
+                        log.lock().unwrap().push(format!("value {}", value));
+                        Ok(())
+                    },
+                )
+                .on_press(|log: Log| async move {
+                    log.lock().unwrap().push("mute".to_string());
+                    Ok(())
+                }),
+            );
+            Ok(Box::new(view))
+        }
+    }
+
+    /// Run the application on the deck until the log holds the expected
+    /// entries.
+    async fn run_until<N>(deck: Arc<VirtualDeck>, expected: &[&str])
+    where
+        N: NavigationEntry<U5, U3, Log>,
+    {
+        let log = Log::default();
+        let run = run_with_input_config::<N, U5, U3, Log>(
+            Theme::default(),
+            RenderConfig::default(),
+            InputConfig::default(),
+            deck,
+            log.clone(),
+            None,
+        );
+        let check = async {
+            while *log.lock().unwrap() != expected {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        let result = tokio::time::timeout(Duration::from_secs(5), async {
+            tokio::select! {
+                result = run => panic!("the run loop stopped: {:?}", result.err()),
+                _ = check => {}
+            }
+        })
+        .await;
+        assert!(
+            result.is_ok(),
+            "expected {:?}, found {:?}",
+            expected,
+            log.lock().unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn encoder_events_reach_the_view() {
+        let deck = Arc::new(VirtualDeck::new(15).with_encoders(4));
+        deck.twist(1, -2);
+        deck.send_event(DeviceStateUpdate::EncoderDown(3));
+        deck.send_event(DeviceStateUpdate::EncoderUp(3));
+        run_until::<Recorded>(deck, &["twist 1 -2", "press 3", "release 3"]).await;
+    }
+
+    #[tokio::test]
+    async fn dial_follows_the_encoder() {
+        let deck = Arc::new(VirtualDeck::new(15).with_encoders(4));
+        deck.twist(0, 3);
+        deck.twist(1, 3);
+        deck.send_event(DeviceStateUpdate::EncoderDown(0));
+        deck.send_event(DeviceStateUpdate::EncoderUp(0));
+        run_until::<Dial>(deck, &["value 53", "mute"]).await;
     }
 }
@@ -0,0 +1,216 @@
+//! Composite screenshots of Stream Deck views.
+//!
+//! This module provides functions for rendering a whole button matrix into
+//! a single image laid out like the physical device. The keys are rendered
+//! with `render_button`, like what is sent to the device, with scrolling
+//! labels shown at their start.
+
+use generic_array::ArrayLength;
+use image::{imageops, DynamicImage, Rgba, RgbaImage};
+
+use crate::{
+    button::{render_button, RenderConfig},
+    view::ButtonMatrix,
+    Theme,
+};
+
+/// Layout of a composite screenshot.
+///
+/// This struct describes the space around and between the keys.
+#[derive(Clone, Copy)]
+pub struct ScreenshotLayout {
+    /// Space between neighbouring keys, in pixels.
+    pub(crate) gap: u32,
+    /// Space between the keys and the edge of the image, in pixels.
+    pub(crate) bezel: u32,
+    /// Color of the space around the keys.
+    pub(crate) background: Rgba<u8>,
+}
+
+impl Default for ScreenshotLayout {
+    fn default() -> Self {
+        ScreenshotLayout {
+            gap: 16,
+            bezel: 24,
+            background: Rgba([0, 0, 0, 255]),
+        }
+    }
+}
+
+impl ScreenshotLayout {
+    /// Create a new screenshot layout.
+    pub fn new(gap: u32, bezel: u32, background: Rgba<u8>) -> Self {
+        ScreenshotLayout {
+            gap,
+            bezel,
+            background,
+        }
+    }
+
+    /// Set the space between neighbouring keys.
+    pub fn with_gap(self, gap: u32) -> Self {
+        ScreenshotLayout { gap, ..self }
+    }
+
+    /// Set the space between the keys and the edge of the image.
+    pub fn with_bezel(self, bezel: u32) -> Self {
+        ScreenshotLayout { bezel, ..self }
+    }
+
+    /// Set the color of the space around the keys.
+    pub fn with_background(self, background: Rgba<u8>) -> Self {
+        ScreenshotLayout { background, ..self }
+    }
+}
+
+/// Render a button matrix to a single image.
+///
+/// Every button is rendered with the given theme and render configuration
+/// and placed in a grid laid out like the physical device.
+pub fn render_screenshot<W, H>(
+    matrix: &ButtonMatrix<W, H>,
+    config: &RenderConfig,
+    theme: &Theme,
+    layout: &ScreenshotLayout,
+) -> Result<DynamicImage, Box<dyn std::error::Error>>
+where
+    W: ArrayLength,
+    H: ArrayLength,
+{
+    let keys: Vec<_> = render_keys(matrix, config, theme)?
+        .into_iter()
+        .map(Some)
+        .collect();
+    Ok(compose_keys(
+        &keys,
+        W::to_usize() as u32,
+        config.width,
+        config.height,
+        layout,
+    ))
+}
+
+/// Render every button of a button matrix to its own image.
+///
+/// The images are returned in button index order.
+pub fn render_keys<W, H>(
+    matrix: &ButtonMatrix<W, H>,
+    config: &RenderConfig,
+    theme: &Theme,
+) -> Result<Vec<DynamicImage>, Box<dyn std::error::Error>>
+where
+    W: ArrayLength,
+    H: ArrayLength,
+{
+    let mut keys = Vec::with_capacity(matrix.size());
+    for row in matrix.buttons.iter() {
+        for button in row.iter() {
+            let config = button.render_config(config);
+            let raw_button = match button.marquee_cycle(&config) {
+                Some(_) => button.to_marquee_render_button(theme, 0),
+                None => button.to_render_button(theme),
+            };
+            keys.push(render_button(&raw_button, &config)?);
+        }
+    }
+    Ok(keys)
+}
+
+/// Compose already rendered key images into a single image.
+///
+/// The keys are given in button index order and wrapped after `columns`
+/// keys. Missing keys are left blank.
+pub fn compose_keys(
+    keys: &[Option<DynamicImage>],
+    columns: u32,
+    key_width: u32,
+    key_height: u32,
+    layout: &ScreenshotLayout,
+) -> DynamicImage {
+    let columns = columns.max(1);
+    let rows = (keys.len() as u32).div_ceil(columns);
+    let width = 2 * layout.bezel + columns * key_width + columns.saturating_sub(1) * layout.gap;
+    let height = 2 * layout.bezel + rows * key_height + rows.saturating_sub(1) * layout.gap;
+
+    let mut image = RgbaImage::from_pixel(width, height, layout.background);
+    for (index, key) in keys.iter().enumerate() {
+        if let Some(key) = key {
+            let column = index as u32 % columns;
+            let row = index as u32 / columns;
+            let x = layout.bezel + column * (key_width + layout.gap);
+            let y = layout.bezel + row * (key_height + layout.gap);
+            let key = imageops::crop_imm(&key.to_rgba8(), 0, 0, key_width, key_height).to_image();
+            imageops::replace(&mut image, &key, x as i64, y as i64);
+        }
+    }
+    DynamicImage::ImageRgba8(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::view::Button;
+    use generic_array::typenum::{U2, U3};
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+
+    fn key(color: Rgba<u8>) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(10, 10, color))
+    }
+
+    #[test]
+    fn keys_are_placed_in_a_grid() {
+        let layout = ScreenshotLayout::new(2, 3, BLUE);
+        let keys = [Some(key(RED)), None, Some(key(RED))];
+        let image = compose_keys(&keys, 2, 10, 10, &layout).to_rgba8();
+
+        assert_eq!(image.dimensions(), (28, 28));
+        assert_eq!(*image.get_pixel(0, 0), BLUE);
+        assert_eq!(*image.get_pixel(3, 3), RED);
+        assert_eq!(*image.get_pixel(12, 12), RED);
+        assert_eq!(*image.get_pixel(13, 3), BLUE);
+        assert_eq!(*image.get_pixel(15, 3), BLUE, "missing key is not blank");
+        assert_eq!(*image.get_pixel(3, 15), RED);
+        assert_eq!(*image.get_pixel(15, 15), BLUE);
+    }
+
+    #[test]
+    fn screenshot_matches_rendered_keys() {
+        let config = RenderConfig::default();
+        let theme = Theme::default();
+        let layout = ScreenshotLayout::default();
+        let mut matrix = ButtonMatrix::<U3, U2>::new();
+        matrix
+            .set_button_by_index(
+                4,
+                Button::text("Scroll this long label".to_string()).with_marquee(20.0),
+            )
+            .unwrap();
+
+        let keys = render_keys(&matrix, &config, &theme).unwrap();
+        assert_eq!(keys.len(), 6);
+        let image = render_screenshot(&matrix, &config, &theme, &layout)
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(
+            image.dimensions(),
+            (
+                2 * layout.bezel + 3 * config.width + 2 * layout.gap,
+                2 * layout.bezel + 2 * config.height + layout.gap
+            )
+        );
+
+        let x = layout.bezel + config.width + layout.gap;
+        let y = layout.bezel + config.height + layout.gap;
+        let shown = imageops::crop_imm(&image, x, y, config.width, config.height).to_image();
+        let button = matrix.get_button_by_index(4).unwrap();
+        let start = render_button(
+            &button.to_marquee_render_button(&theme, 0),
+            &button.render_config(&config),
+        )
+        .unwrap();
+        assert!(shown == start.to_rgba8());
+        assert!(keys[4] == start);
+    }
+}
@@ -0,0 +1,13 @@
+//! Test support for Stream Deck applications.
+//!
+//! This module provides helpers for testing views without a physical
+//! Stream Deck. It is only available with the `testing` feature.
+
+mod script;
+mod snapshot;
+mod view_tester;
+
+// Re-export public items
+pub use self::script::{Script, ScriptFailure, ScriptRunner, Step};
+pub use self::snapshot::{render_view_keys, Snapshots, UPDATE_SNAPSHOTS_ENV};
+pub use self::view_tester::ViewTester;
@@ -0,0 +1,496 @@
+//! Scripted input replay for Stream Deck applications.
+//!
+//! This module parses interaction scripts and replays them against an
+//! application through the input handlers of `DisplayManager`.
+//!
+//! A script has one step per line. Empty lines and lines starting with
+//! `#` are ignored.
+//!
+//! ```text
+//! # Toggle the microphone and go to the settings view
+//! press 3
+//! wait 500
+//! release 3
+//! expect text 3 "On"
+//! expect state 3 active
+//! click 4
+//! expect navigation Settings
+//! # Turn the first dial two ticks counter-clockwise
+//! twist 0 -2
+//! ```
+//!
+//! Navigation entries are compared with their `Debug` representation.
+
+use std::{fmt, marker::PhantomData, path::Path, sync::Arc, time::{Duration, Instant}};
+
+use generic_array::ArrayLength;
+use image::DynamicImage;
+use tokio::sync::mpsc;
+
+use crate::{
+    button::RenderConfig,
+    device::VirtualDeck,
+    input::InputConfig,
+    navigation::NavigationEntry,
+    screenshot::ScreenshotLayout,
+    view::{ButtonMatrix, ButtonState, DisplayManager},
+    Theme,
+};
+
+/// A step of an interaction script.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Step {
+    /// Press the key at the given index.
+    Press(u8),
+    /// Release the key at the given index.
+    Release(u8),
+    /// Press and release the key at the given index.
+    Click(u8),
+    /// Twist the encoder at the given index by the given number of ticks.
+    Twist(u8, i8),
+    /// Wait for the given duration.
+    Wait(Duration),
+    /// Expect the current navigation entry to have the given `Debug` form.
+    ExpectNavigation(String),
+    /// Expect the key at the given index to show the given text.
+    ExpectText(u8, String),
+    /// Expect the key at the given index to be in the given state.
+    ExpectState(u8, ButtonState),
+}
+
+/// An interaction script.
+///
+/// This struct holds the parsed steps of a script together with the
+/// line numbers they came from.
+#[derive(Clone, Debug, Default)]
+pub struct Script {
+    /// The steps and their line numbers.
+    pub(crate) steps: Vec<(usize, Step)>,
+}
+
+impl Script {
+    /// Parse a script from a string.
+    pub fn parse(source: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut steps = Vec::new();
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let step = parse_step(line).map_err(|e| format!("line {}: {}", index + 1, e))?;
+            steps.push((index + 1, step));
+        }
+        Ok(Script { steps })
+    }
+
+    /// Read and parse a script from a file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let source = std::fs::read_to_string(path)?;
+        Script::parse(&source)
+    }
+
+    /// Get the steps of the script.
+    pub fn steps(&self) -> impl Iterator<Item = &Step> {
+        self.steps.iter().map(|(_, step)| step)
+    }
+}
+
+fn parse_step(line: &str) -> Result<Step, String> {
+    let (command, rest) = split_word(line);
+    match command {
+        "press" => Ok(Step::Press(parse_key(rest)?)),
+        "release" => Ok(Step::Release(parse_key(rest)?)),
+        "click" => Ok(Step::Click(parse_key(rest)?)),
+        "twist" => {
+            let (encoder, ticks) = split_word(rest);
+            let ticks = ticks
+                .parse::<i8>()
+                .map_err(|_| format!("invalid ticks: {}", ticks))?;
+            Ok(Step::Twist(parse_key(encoder)?, ticks))
+        }
+        "wait" => {
+            let millis = rest
+                .parse::<u64>()
+                .map_err(|_| format!("invalid duration: {}", rest))?;
+            Ok(Step::Wait(Duration::from_millis(millis)))
+        }
+        "expect" => {
+            let (kind, rest) = split_word(rest);
+            match kind {
+                "navigation" => Ok(Step::ExpectNavigation(unquote(rest).to_string())),
+                "text" => {
+                    let (key, text) = split_word(rest);
+                    Ok(Step::ExpectText(parse_key(key)?, unquote(text).to_string()))
+                }
+                "state" => {
+                    let (key, state) = split_word(rest);
+                    Ok(Step::ExpectState(parse_key(key)?, parse_state(state)?))
+                }
+                _ => Err(format!("unknown expectation: {}", kind)),
+            }
+        }
+        _ => Err(format!("unknown command: {}", command)),
+    }
+}
+
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim();
+    match text.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim()),
+        None => (text, ""),
+    }
+}
+
+fn unquote(text: &str) -> &str {
+    text.strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+        .unwrap_or(text)
+}
+
+fn parse_key(text: &str) -> Result<u8, String> {
+    text.parse::<u8>()
+        .map_err(|_| format!("invalid key index: {}", text))
+}
+
+fn parse_state(text: &str) -> Result<ButtonState, String> {
+    match text {
+        "default" => Ok(ButtonState::Default),
+        "pressed" => Ok(ButtonState::Pressed),
+        "active" => Ok(ButtonState::Active),
+        "inactive" => Ok(ButtonState::Inactive),
+        "error" => Ok(ButtonState::Error),
+        _ => Err(format!("unknown button state: {}", text)),
+    }
+}
+
+/// A failed step of an interaction script.
+///
+/// This struct describes which step failed and why, together with the
+/// view as it looked at that point.
+pub struct ScriptFailure {
+    /// The line number of the failed step.
+    pub(crate) line: usize,
+    /// The failed step.
+    pub(crate) step: Step,
+    /// The reason for the failure.
+    pub(crate) message: String,
+    /// A text dump of the rendered button matrix.
+    pub(crate) matrix: String,
+    /// A screenshot of the rendered view.
+    pub(crate) screenshot: Option<DynamicImage>,
+}
+
+impl ScriptFailure {
+    /// Get the line number of the failed step.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Get the failed step.
+    pub fn step(&self) -> &Step {
+        &self.step
+    }
+
+    /// Get the reason for the failure.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Get a text dump of the rendered button matrix.
+    pub fn matrix(&self) -> &str {
+        &self.matrix
+    }
+
+    /// Get a screenshot of the rendered view.
+    pub fn screenshot(&self) -> Option<&DynamicImage> {
+        self.screenshot.as_ref()
+    }
+}
+
+impl fmt::Display for ScriptFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Script failed at line {} ({:?}): {}",
+            self.line, self.step, self.message
+        )?;
+        write!(f, "{}", self.matrix)
+    }
+}
+
+impl fmt::Debug for ScriptFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for ScriptFailure {}
+
+/// A harness for replaying interaction scripts.
+///
+/// This struct owns a display manager connected to a virtual Stream Deck
+/// and processes navigation the same way the run loop does.
+pub struct ScriptRunner<N, W, H, C>
+where
+    N: NavigationEntry<W, H, C>,
+    W: ArrayLength,
+    H: ArrayLength,
+    C: Send + Clone + Sync + 'static,
+{
+    /// The display manager.
+    pub(crate) manager: DisplayManager<N, W, H, C>,
+    /// The receiver for navigation events.
+    pub(crate) navigation_receiver: mpsc::Receiver<N>,
+    /// The virtual Stream Deck.
+    pub(crate) deck: Arc<VirtualDeck>,
+    /// Phantom data for the width and height.
+    pub(crate) _marker: PhantomData<(W, H)>,
+}
+
+impl<N, W, H, C> ScriptRunner<N, W, H, C>
+where
+    N: NavigationEntry<W, H, C> + fmt::Debug,
+    W: ArrayLength,
+    H: ArrayLength,
+    C: Send + Clone + Sync + 'static,
+{
+    /// Create a new script runner.
+    ///
+    /// This method starts the application on a virtual Stream Deck and
+    /// renders the default view.
+    pub async fn new(
+        config: RenderConfig,
+        theme: Theme,
+        context: C,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let deck = Arc::new(VirtualDeck::new((W::to_usize() * H::to_usize()) as u8));
+        let (manager, navigation_receiver) =
+            DisplayManager::new(deck.clone(), config, theme, context).await?;
+        manager.fetch_all().await?;
+        manager.render().await?;
+        Ok(ScriptRunner {
+            manager,
+            navigation_receiver,
+            deck,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Set the input configuration.
+    ///
+    /// This method sets the timings used to recognize gestures.
+    pub fn with_input_config(self, input: InputConfig) -> Self {
+        ScriptRunner {
+            manager: self.manager.with_input_config(input),
+            ..self
+        }
+    }
+
+    /// Get the display manager.
+    pub fn manager(&self) -> &DisplayManager<N, W, H, C> {
+        &self.manager
+    }
+
+    /// Get the virtual Stream Deck.
+    pub fn deck(&self) -> &Arc<VirtualDeck> {
+        &self.deck
+    }
+
+    /// Replay a script.
+    ///
+    /// This method stops at the first failed step and returns a description
+    /// of the failure.
+    pub async fn run(&mut self, script: &Script) -> Result<(), ScriptFailure> {
+        for (line, step) in script.steps.iter() {
+            if let Err(message) = self.run_step(step).await {
+                return Err(self.failure(*line, step, message).await);
+            }
+        }
+        Ok(())
+    }
+
+    async fn run_step(&mut self, step: &Step) -> Result<(), String> {
+        match step {
+            Step::Press(key) => {
+                self.manager.on_press(*key).await.map_err(|e| e.to_string())?;
+            }
+            Step::Release(key) => {
+                self.manager.on_release(*key).await.map_err(|e| e.to_string())?;
+            }
+            Step::Click(key) => {
+                self.manager.on_press(*key).await.map_err(|e| e.to_string())?;
+                self.manager.on_release(*key).await.map_err(|e| e.to_string())?;
+            }
+            Step::Twist(encoder, ticks) => {
+                self.manager
+                    .on_encoder_twist(*encoder, *ticks)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            }
+            Step::Wait(duration) => {
+                let end = Instant::now() + *duration;
+                while let Some(deadline) = self.manager.next_deadline().filter(|d| *d <= end) {
+                    tokio::time::sleep_until(deadline.into()).await;
+                    self.manager.on_tick().await.map_err(|e| e.to_string())?;
+                    self.process_navigation().await?;
+                }
+                tokio::time::sleep_until(end.into()).await;
+            }
+            Step::ExpectNavigation(expected) => {
+                let current = self
+                    .manager
+                    .get_current_navigation()
+                    .await
+                    .map_err(|e| e.to_string())?;
+                let current = format!("{:?}", current);
+                if &current != expected {
+                    return Err(format!(
+                        "expected navigation {}, found {}",
+                        expected, current
+                    ));
+                }
+            }
+            Step::ExpectText(key, expected) => {
+                let matrix = self.matrix().await?;
+                let button = matrix
+                    .get_button_by_index(*key as usize)
+                    .ok_or(format!("key {} is out of bounds", key))?;
+                if button.get_text() != expected {
+                    return Err(format!(
+                        "expected key {} to show {:?}, found {:?}",
+                        key,
+                        expected,
+                        button.get_text()
+                    ));
+                }
+            }
+            Step::ExpectState(key, expected) => {
+                let matrix = self.matrix().await?;
+                let button = matrix
+                    .get_button_by_index(*key as usize)
+                    .ok_or(format!("key {} is out of bounds", key))?;
+                if button.get_state() != *expected {
+                    return Err(format!(
+                        "expected key {} to be {:?}, found {:?}",
+                        key,
+                        expected,
+                        button.get_state()
+                    ));
+                }
+            }
+        }
+        self.process_navigation().await
+    }
+
+    /// Apply pending navigation events, like the run loop does.
+    async fn process_navigation(&mut self) -> Result<(), String> {
+        while let Ok(navigation) = self.navigation_receiver.try_recv() {
+            self.manager
+                .navigate_to(navigation)
+                .await
+                .map_err(|e| e.to_string())?;
+            self.manager.fetch_all().await.map_err(|e| e.to_string())?;
+            self.manager.render().await.map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Get the button matrix as it is shown on the virtual Stream Deck.
+    async fn matrix(&self) -> Result<ButtonMatrix<W, H>, String> {
+        self.manager
+            .rendered_matrix()
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn failure(&self, line: usize, step: &Step, message: String) -> ScriptFailure {
+        let matrix = match self.matrix().await {
+            Ok(matrix) => dump_matrix(&matrix),
+            Err(e) => format!("<failed to render view: {}>", e),
+        };
+        let screenshot = self
+            .manager
+            .screenshot(&ScreenshotLayout::default())
+            .await
+            .ok();
+        ScriptFailure {
+            line,
+            step: step.clone(),
+            message,
+            matrix,
+            screenshot,
+        }
+    }
+}
+
+/// Dump a button matrix as text, one row per line.
+fn dump_matrix<W, H>(matrix: &ButtonMatrix<W, H>) -> String
+where
+    W: ArrayLength,
+    H: ArrayLength,
+{
+    let mut dump = String::new();
+    for (y, row) in matrix.buttons.iter().enumerate() {
+        let cells: Vec<String> = row
+            .iter()
+            .enumerate()
+            .map(|(x, button)| {
+                format!(
+                    "{:>2}: {:?} ({:?})",
+                    y * W::to_usize() + x,
+                    button.get_text(),
+                    button.get_state()
+                )
+            })
+            .collect();
+        dump.push_str(&cells.join(" | "));
+        dump.push('\n');
+    }
+    dump
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        button::Icon,
+        view::{
+            customizable::{ClickButton, CustomizableView},
+            View,
+        },
+    };
+    use generic_array::typenum::{U3, U5};
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct Home;
+
+    impl NavigationEntry<U5, U3, ()> for Home {
+        async fn get_view(
+            &self,
+            _context: (),
+        ) -> Result<Box<dyn View<U5, U3, (), Home>>, Box<dyn std::error::Error>> {
+            let mut view = CustomizableView::new();
+            view.set_button(0, 0, ClickButton::new("Click", None::<Icon>, |_| async { Ok(()) }))?;
+            Ok(Box::new(view))
+        }
+    }
+
+    #[tokio::test]
+    async fn held_key_is_pressed() {
+        let mut runner = ScriptRunner::<Home, U5, U3, ()>::new(
+            RenderConfig::default(),
+            Theme::default(),
+            (),
+        )
+        .await
+        .unwrap();
+        let script = Script::parse(
+            "press 0\nexpect state 0 pressed\nexpect state 1 default\n\
+             release 0\nexpect state 0 default",
+        )
+        .unwrap();
+        if let Err(failure) = runner.run(&script).await {
+            panic!("line {}: {}", failure.line(), failure.message());
+        }
+    }
+}
@@ -0,0 +1,290 @@
+//! Golden-image snapshot testing for views.
+//!
+//! This module renders views to per-key images and compares them with
+//! PNG snapshots stored on disk.
+
+use std::path::{Path, PathBuf};
+
+use generic_array::ArrayLength;
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use crate::{
+    button::RenderConfig,
+    navigation::NavigationEntry,
+    screenshot::render_keys,
+    view::View,
+    Theme,
+};
+
+/// Environment variable that makes snapshot comparisons overwrite the
+/// stored snapshots instead of failing.
+pub const UPDATE_SNAPSHOTS_ENV: &str = "STREAMDECK_UPDATE_SNAPSHOTS";
+
+/// Render a view to one image per key.
+///
+/// The images are returned in button index order and are identical to
+/// what the display manager sends to the device, with scrolling labels
+/// shown at their start.
+pub async fn render_view_keys<W, H, C, N>(
+    view: &dyn View<W, H, C, N>,
+    config: &RenderConfig,
+    theme: &Theme,
+) -> Result<Vec<DynamicImage>, Box<dyn std::error::Error>>
+where
+    W: ArrayLength,
+    H: ArrayLength,
+    C: Send + Clone + Sync + 'static,
+    N: NavigationEntry<W, H, C>,
+{
+    let button_matrix = view.render().await?;
+    render_keys(&button_matrix, config, theme)
+}
+
+/// A directory of golden-image snapshots.
+///
+/// Every snapshot is a directory containing one PNG per key. When a key
+/// does not match its snapshot, the rendered image and a diff image are
+/// written next to it. Setting the `STREAMDECK_UPDATE_SNAPSHOTS`
+/// environment variable regenerates the snapshots instead.
+pub struct Snapshots {
+    /// The directory containing the snapshots.
+    pub(crate) dir: PathBuf,
+    /// The largest per-channel difference that is still a match.
+    pub(crate) tolerance: u8,
+    /// The number of pixels per key allowed to exceed the tolerance.
+    pub(crate) max_mismatched_pixels: usize,
+}
+
+impl Snapshots {
+    /// Create a new snapshot directory.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Snapshots {
+            dir: dir.into(),
+            tolerance: 2,
+            max_mismatched_pixels: 0,
+        }
+    }
+
+    /// Set the largest per-channel difference that is still a match.
+    pub fn with_tolerance(self, tolerance: u8) -> Self {
+        Snapshots { tolerance, ..self }
+    }
+
+    /// Set the number of pixels per key allowed to exceed the tolerance.
+    pub fn with_max_mismatched_pixels(self, max_mismatched_pixels: usize) -> Self {
+        Snapshots {
+            max_mismatched_pixels,
+            ..self
+        }
+    }
+
+    /// Compare a view with the snapshot of the given name.
+    pub async fn assert_view<W, H, C, N>(
+        &self,
+        name: &str,
+        view: &dyn View<W, H, C, N>,
+        config: &RenderConfig,
+        theme: &Theme,
+    ) -> Result<(), Box<dyn std::error::Error>>
+    where
+        W: ArrayLength,
+        H: ArrayLength,
+        C: Send + Clone + Sync + 'static,
+        N: NavigationEntry<W, H, C>,
+    {
+        let keys = render_view_keys(view, config, theme).await?;
+        self.assert_keys(name, &keys)
+    }
+
+    /// Compare key images with the snapshot of the given name.
+    pub fn assert_keys(
+        &self,
+        name: &str,
+        keys: &[DynamicImage],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let dir = self.dir.join(name);
+        std::fs::create_dir_all(&dir)?;
+
+        if std::env::var_os(UPDATE_SNAPSHOTS_ENV).is_some() {
+            for (index, key) in keys.iter().enumerate() {
+                key.save(key_path(&dir, index, None))?;
+                remove_if_exists(&key_path(&dir, index, Some("actual")))?;
+                remove_if_exists(&key_path(&dir, index, Some("diff")))?;
+            }
+            return Ok(());
+        }
+
+        let mut failures = Vec::new();
+        for (index, key) in keys.iter().enumerate() {
+            let path = key_path(&dir, index, None);
+            let actual = key.to_rgba8();
+            let expected = match image::open(&path) {
+                Ok(expected) => expected.to_rgba8(),
+                Err(_) => {
+                    key.save(key_path(&dir, index, Some("actual")))?;
+                    failures.push(format!("key {}: missing snapshot {}", index, path.display()));
+                    continue;
+                }
+            };
+
+            if expected.dimensions() != actual.dimensions() {
+                key.save(key_path(&dir, index, Some("actual")))?;
+                failures.push(format!(
+                    "key {}: size {:?} does not match snapshot size {:?}",
+                    index,
+                    actual.dimensions(),
+                    expected.dimensions()
+                ));
+                continue;
+            }
+
+            let (diff, mismatched) = self.diff(&expected, &actual);
+            if mismatched > self.max_mismatched_pixels {
+                key.save(key_path(&dir, index, Some("actual")))?;
+                let diff_path = key_path(&dir, index, Some("diff"));
+                diff.save(&diff_path)?;
+                failures.push(format!(
+                    "key {}: {} pixels differ, diff written to {}",
+                    index,
+                    mismatched,
+                    diff_path.display()
+                ));
+            } else {
+                remove_if_exists(&key_path(&dir, index, Some("actual")))?;
+                remove_if_exists(&key_path(&dir, index, Some("diff")))?;
+            }
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Snapshot {} does not match (set {} to update):\n{}",
+                name,
+                UPDATE_SNAPSHOTS_ENV,
+                failures.join("\n")
+            )
+            .into())
+        }
+    }
+
+    /// Build a diff image and count the pixels exceeding the tolerance.
+    ///
+    /// Mismatched pixels are drawn in red over a faded copy of the
+    /// expected image.
+    fn diff(&self, expected: &RgbaImage, actual: &RgbaImage) -> (RgbaImage, usize) {
+        let mut mismatched = 0;
+        let diff = RgbaImage::from_fn(expected.width(), expected.height(), |x, y| {
+            let expected_pixel = expected.get_pixel(x, y);
+            let actual_pixel = actual.get_pixel(x, y);
+            let exceeds = expected_pixel
+                .0
+                .iter()
+                .zip(actual_pixel.0.iter())
+                .any(|(a, b)| a.abs_diff(*b) > self.tolerance);
+            if exceeds {
+                mismatched += 1;
+                Rgba([255, 0, 0, 255])
+            } else {
+                let luma = expected_pixel.0[..3].iter().map(|c| *c as u32).sum::<u32>() / 3;
+                let faded = (luma / 4) as u8;
+                Rgba([faded, faded, faded, 255])
+            }
+        });
+        (diff, mismatched)
+    }
+}
+
+fn key_path(dir: &Path, index: usize, suffix: Option<&str>) -> PathBuf {
+    match suffix {
+        Some(suffix) => dir.join(format!("key_{}.{}.png", index, suffix)),
+        None => dir.join(format!("key_{}.png", index)),
+    }
+}
+
+fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A snapshot directory that is removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "streamdeck-oxide-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = std::fs::remove_dir_all(&dir);
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn key(value: u8) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 8, Rgba([value, value, value, 255])))
+    }
+
+    #[test]
+    fn missing_snapshot_fails() {
+        let dir = TempDir::new("missing");
+        let snapshots = Snapshots::new(&dir.0);
+        let error = snapshots.assert_keys("view", &[key(100)]).unwrap_err();
+        assert!(error.to_string().contains("missing snapshot"));
+        assert!(dir.0.join("view").join("key_0.actual.png").exists());
+    }
+
+    #[test]
+    fn matching_keys_pass() {
+        let dir = TempDir::new("matching");
+        let snapshots = Snapshots::new(&dir.0);
+        std::fs::create_dir_all(dir.0.join("view")).unwrap();
+        key(100).save(dir.0.join("view").join("key_0.png")).unwrap();
+
+        snapshots.assert_keys("view", &[key(100)]).unwrap();
+        snapshots.assert_keys("view", &[key(102)]).unwrap();
+        assert!(snapshots.assert_keys("view", &[key(103)]).is_err());
+        snapshots
+            .with_tolerance(3)
+            .assert_keys("view", &[key(103)])
+            .unwrap();
+    }
+
+    #[test]
+    fn mismatch_writes_diff() {
+        let dir = TempDir::new("mismatch");
+        let snapshots = Snapshots::new(&dir.0);
+        let view = dir.0.join("view");
+        std::fs::create_dir_all(&view).unwrap();
+        key(100).save(view.join("key_0.png")).unwrap();
+
+        let mut changed = key(100).to_rgba8();
+        changed.put_pixel(1, 1, Rgba([255, 255, 255, 255]));
+        let changed = [DynamicImage::ImageRgba8(changed)];
+        let error = snapshots.assert_keys("view", &changed).unwrap_err();
+        assert!(error.to_string().contains("1 pixels differ"));
+        let diff = image::open(view.join("key_0.diff.png")).unwrap().to_rgba8();
+        assert_eq!(*diff.get_pixel(1, 1), Rgba([255, 0, 0, 255]));
+        assert_eq!(*diff.get_pixel(0, 0), Rgba([25, 25, 25, 255]));
+
+        snapshots
+            .with_max_mismatched_pixels(1)
+            .assert_keys("view", &changed)
+            .unwrap();
+        assert!(!view.join("key_0.actual.png").exists());
+        assert!(!view.join("key_0.diff.png").exists());
+    }
+}
@@ -0,0 +1,269 @@
+//! Unit-testing harness for views.
+//!
+//! This module provides a harness that owns a view and a context and
+//! records the navigation events the view sends.
+
+use std::{future::Future, sync::Arc};
+
+use generic_array::ArrayLength;
+use tokio::sync::mpsc;
+
+use crate::{
+    input::{MultiTap, TouchEvent},
+    navigation::NavigationEntry,
+    view::{ButtonMatrix, Strip, View},
+};
+
+/// A harness for testing a single view.
+///
+/// This struct calls the view directly, without a display manager or
+/// a device, and captures everything the view sends to the navigation
+/// sender.
+pub struct ViewTester<W, H, C, N>
+where
+    W: ArrayLength,
+    H: ArrayLength,
+    C: Send + Clone + Sync + 'static,
+    N: NavigationEntry<W, H, C>,
+{
+    /// The view under test.
+    pub(crate) view: Box<dyn View<W, H, C, N>>,
+    /// The application context.
+    pub(crate) context: C,
+    /// The sender passed to the view.
+    pub(crate) navigation_sender: Arc<mpsc::Sender<N>>,
+    /// The receiver for navigation events.
+    pub(crate) navigation_receiver: mpsc::Receiver<N>,
+    /// The navigation events sent so far.
+    pub(crate) navigations: Vec<N>,
+}
+
+impl<W, H, C, N> ViewTester<W, H, C, N>
+where
+    W: ArrayLength,
+    H: ArrayLength,
+    C: Send + Clone + Sync + 'static,
+    N: NavigationEntry<W, H, C>,
+{
+    /// Create a new view tester.
+    pub fn new(view: impl View<W, H, C, N>, context: C) -> Self {
+        ViewTester::from_boxed(Box::new(view), context)
+    }
+
+    /// Create a new view tester for a boxed view.
+    pub fn from_boxed(view: Box<dyn View<W, H, C, N>>, context: C) -> Self {
+        let (sender, receiver) = mpsc::channel(64);
+        ViewTester {
+            view,
+            context,
+            navigation_sender: Arc::new(sender),
+            navigation_receiver: receiver,
+            navigations: Vec::new(),
+        }
+    }
+
+    /// Create a new view tester for the view of a navigation entry.
+    pub async fn for_navigation(
+        navigation: &N,
+        context: C,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let view = navigation.get_view(context.clone()).await?;
+        Ok(ViewTester::from_boxed(view, context))
+    }
+
+    /// Click the button at the given coordinates.
+    pub async fn click(&mut self, x: usize, y: usize) -> Result<(), Box<dyn std::error::Error>> {
+        let index = Self::index(x, y)?;
+        self.click_index(index).await
+    }
+
+    /// Click the button at the given index.
+    pub async fn click_index(&mut self, index: u8) -> Result<(), Box<dyn std::error::Error>> {
+        let handler = self
+            .view
+            .on_click(&self.context, index, self.navigation_sender.clone());
+        collect_navigations(&mut self.navigation_receiver, &mut self.navigations, handler).await
+    }
+
+    /// Long press the button at the given coordinates.
+    pub async fn long_press(&mut self, x: usize, y: usize) -> Result<(), Box<dyn std::error::Error>> {
+        let index = Self::index(x, y)?;
+        let handler = self
+            .view
+            .on_long_press(&self.context, index, self.navigation_sender.clone());
+        collect_navigations(&mut self.navigation_receiver, &mut self.navigations, handler).await
+    }
+
+    /// Tap the button at the given coordinates several times in quick
+    /// succession.
+    pub async fn multi_tap(
+        &mut self,
+        x: usize,
+        y: usize,
+        tap: MultiTap,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let index = Self::index(x, y)?;
+        let handler = self
+            .view
+            .on_multi_tap(&self.context, index, tap, self.navigation_sender.clone());
+        collect_navigations(&mut self.navigation_receiver, &mut self.navigations, handler).await
+    }
+
+    /// Hold the buttons at the given indices at once.
+    pub async fn chord(&mut self, keys: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        let handler = self
+            .view
+            .on_chord(&self.context, keys, self.navigation_sender.clone());
+        collect_navigations(&mut self.navigation_receiver, &mut self.navigations, handler).await
+    }
+
+    /// Twist the encoder at the given index.
+    pub async fn twist(&mut self, encoder: u8, ticks: i8) -> Result<(), Box<dyn std::error::Error>> {
+        let handler = self
+            .view
+            .on_encoder_twist(&self.context, encoder, ticks, self.navigation_sender.clone());
+        collect_navigations(&mut self.navigation_receiver, &mut self.navigations, handler).await
+    }
+
+    /// Press and release the encoder at the given index.
+    pub async fn press_encoder(&mut self, encoder: u8) -> Result<(), Box<dyn std::error::Error>> {
+        let (view, context, sender) = (&self.view, &self.context, &self.navigation_sender);
+        let handler = async move {
+            view.on_encoder_press(context, encoder, sender.clone()).await?;
+            view.on_encoder_release(context, encoder, sender.clone()).await
+        };
+        collect_navigations(&mut self.navigation_receiver, &mut self.navigations, handler).await
+    }
+
+    /// Send a touch strip gesture to the view.
+    pub async fn touch(&mut self, event: TouchEvent) -> Result<(), Box<dyn std::error::Error>> {
+        let handler = self
+            .view
+            .on_touch(&self.context, event, self.navigation_sender.clone());
+        collect_navigations(&mut self.navigation_receiver, &mut self.navigations, handler).await
+    }
+
+    /// Fetch state for all buttons in the view.
+    pub async fn fetch(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.view.fetch_all(&self.context).await
+    }
+
+    /// Render the view to a button matrix.
+    pub async fn rendered(&self) -> Result<ButtonMatrix<W, H>, Box<dyn std::error::Error>> {
+        self.view.render().await
+    }
+
+    /// Render the touch strip of the view.
+    pub async fn rendered_strip(&self) -> Result<Option<Strip>, Box<dyn std::error::Error>> {
+        self.view.render_strip().await
+    }
+
+    /// Get the navigation events sent by the view so far.
+    pub fn navigations_sent(&self) -> &[N] {
+        &self.navigations
+    }
+
+    /// Take the navigation events sent by the view so far.
+    pub fn take_navigations(&mut self) -> Vec<N> {
+        std::mem::take(&mut self.navigations)
+    }
+
+    /// Get the view under test.
+    pub fn view(&self) -> &dyn View<W, H, C, N> {
+        self.view.as_ref()
+    }
+
+    /// Get the application context.
+    pub fn context(&self) -> &C {
+        &self.context
+    }
+
+    fn index(x: usize, y: usize) -> Result<u8, Box<dyn std::error::Error>> {
+        if x < W::to_usize() && y < H::to_usize() {
+            Ok((y * W::to_usize() + x) as u8)
+        } else {
+            Err(Box::new(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Button index out of bounds",
+            )))
+        }
+    }
+}
+
+/// Run a view handler and record the navigation events it sends.
+///
+/// The events are received while the handler runs, so that a handler
+/// sending more events than the channel holds does not block.
+async fn collect_navigations<N>(
+    receiver: &mut mpsc::Receiver<N>,
+    navigations: &mut Vec<N>,
+    handler: impl Future<Output = Result<(), Box<dyn std::error::Error>>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    tokio::pin!(handler);
+    loop {
+        tokio::select! {
+            result = &mut handler => {
+                while let Ok(navigation) = receiver.try_recv() {
+                    navigations.push(navigation);
+                }
+                return result;
+            }
+            Some(navigation) = receiver.recv() => navigations.push(navigation),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use generic_array::typenum::{U1, U2};
+    use std::time::Duration;
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct Screen(u8);
+
+    impl NavigationEntry<U2, U1, ()> for Screen {
+        async fn get_view(
+            &self,
+            _context: (),
+        ) -> Result<Box<dyn View<U2, U1, (), Screen>>, Box<dyn std::error::Error>> {
+            Ok(Box::new(Flood))
+        }
+    }
+
+    /// A view that navigates many times on every click.
+    struct Flood;
+
+    #[async_trait::async_trait]
+    impl View<U2, U1, (), Screen> for Flood {
+        async fn render(&self) -> Result<ButtonMatrix<U2, U1>, Box<dyn std::error::Error>> {
+            Ok(ButtonMatrix::new())
+        }
+
+        async fn on_click(
+            &self,
+            _context: &(),
+            index: u8,
+            navigation: Arc<mpsc::Sender<Screen>>,
+        ) -> Result<(), Box<dyn std::error::Error>> {
+            for _ in 0..200 {
+                navigation.send(Screen(index)).await?;
+            }
+            Ok(())
+        }
+
+        async fn fetch_all(&self, _context: &()) -> Result<(), Box<dyn std::error::Error>> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn records_many_navigations() {
+        let mut tester = ViewTester::for_navigation(&Screen(0), ()).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), tester.click(1, 0))
+            .await
+            .expect("the view blocked on sending")
+            .unwrap();
+        assert_eq!(tester.take_navigations(), vec![Screen(1); 200]);
+    }
+}
@@ -2,10 +2,15 @@
 //!
 //! This module provides types for representing buttons in the view system.
 
-use crate::Theme;
+use resvg::tiny_skia::Color;
+
+use crate::{
+    button::{marquee_cycle, FontChain, FontWeight, Icon, Layout, RasterImage, RenderConfig},
+    Theme,
+};
 
 /// The state of a button.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ButtonState {
     /// The default state.
     Default,
@@ -23,26 +28,38 @@ pub enum ButtonState {
 ///
 /// This struct represents a button in the view system. It contains
 /// the text, icon, and state of the button.
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct Button {
     /// The text to display on the button.
     pub(crate) text: String,
     /// The icon to display on the button.
-    pub(crate) icon: Option<&'static str>,
+    pub(crate) icon: Option<Icon>,
+    /// The raster image to display on the button, drawn instead of the
+    /// icon.
+    pub(crate) image: Option<RasterImage>,
     /// The state of the button.
     pub(crate) state: ButtonState,
     /// Alternative theme
     pub(crate) theme: Option<Theme>,
+    /// The scrolling speed of the label in pixels per second, if it
+    /// scrolls when it does not fit.
+    pub(crate) marquee: Option<f32>,
+    /// Alternative layout of the label and the icon
+    pub(crate) layout: Option<Layout>,
+    /// Alternative fonts for the label
+    pub(crate) font: Option<FontChain>,
+    /// Alternative font weight for the label
+    pub(crate) font_weight: Option<FontWeight>,
 }
 
 impl Button {
     /// Create a new button with the given text, icon, and state.
-    pub fn new(text: String, icon: Option<&'static str>, state: ButtonState) -> Self {
+    pub fn new(text: String, icon: Option<impl Into<Icon>>, state: ButtonState) -> Self {
         Button {
             text,
-            icon,
+            icon: icon.map(Into::into),
             state,
-            theme: None,
+            ..Button::default()
         }
     }
 
@@ -50,19 +67,16 @@ impl Button {
     pub fn text(text: String) -> Self {
         Button {
             text,
-            icon: None,
-            state: ButtonState::Default,
-            theme: None,
+            ..Button::default()
         }
     }
 
     /// Create a new button with the given text and icon.
-    pub fn with_icon(text: String, icon: &'static str) -> Self {
+    pub fn with_icon(text: String, icon: impl Into<Icon>) -> Self {
         Button {
             text,
-            icon: Some(icon),
-            state: ButtonState::Default,
-            theme: None,
+            icon: Some(icon.into()),
+            ..Button::default()
         }
     }
 
@@ -70,49 +84,81 @@ impl Button {
     pub fn with_state(text: String, state: ButtonState) -> Self {
         Button {
             text,
-            icon: None,
             state,
-            theme: None,
+            ..Button::default()
         }
     }
 
     /// Create a new button with the given text, icon, and state.
-    pub fn with_icon_and_state(text: String, icon: &'static str, state: ButtonState) -> Self {
+    pub fn with_icon_and_state(text: String, icon: impl Into<Icon>, state: ButtonState) -> Self {
         Button {
             text,
-            icon: Some(icon),
+            icon: Some(icon.into()),
             state,
-            theme: None,
+            ..Button::default()
+        }
+    }
+
+    /// Create a new button with the given caption and raster image.
+    ///
+    /// The caption is drawn over the image unless it is empty. It is
+    /// wrapped and shrunk to fit, but never scrolled.
+    pub fn with_image(text: String, image: impl Into<RasterImage>) -> Self {
+        Button {
+            image: Some(image.into()),
+            ..Button::text(text)
         }
     }
 
+    /// Get the text of the button.
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    /// Get the icon of the button.
+    pub fn get_icon(&self) -> Option<&Icon> {
+        self.icon.as_ref()
+    }
+
+    /// Get the raster image of the button.
+    pub fn get_image(&self) -> Option<&RasterImage> {
+        self.image.as_ref()
+    }
+
+    /// Get the state of the button.
+    pub fn get_state(&self) -> ButtonState {
+        self.state
+    }
+
     /// Update the text of the button.
     pub fn updated_text(&self, text: String) -> Self {
         Button {
             text,
-            icon: self.icon,
-            state: self.state,
-            theme: self.theme.clone(),
+            ..self.clone()
         }
     }
 
     /// Update the icon of the button.
-    pub fn updated_icon(&self, icon: &'static str) -> Self {
+    pub fn updated_icon(&self, icon: impl Into<Icon>) -> Self {
         Button {
-            text: self.text.clone(),
-            icon: Some(icon),
-            state: self.state,
-            theme: self.theme.clone(),
+            icon: Some(icon.into()),
+            ..self.clone()
+        }
+    }
+
+    /// Update the raster image of the button.
+    pub fn updated_image(&self, image: impl Into<RasterImage>) -> Self {
+        Button {
+            image: Some(image.into()),
+            ..self.clone()
         }
     }
 
     /// Update the state of the button.
     pub fn updated_state(&self, state: ButtonState) -> Self {
         Button {
-            text: self.text.clone(),
-            icon: self.icon,
             state,
-            theme: self.theme.clone(),
+            ..self.clone()
         }
     }
 
@@ -123,6 +169,164 @@ impl Button {
             ..self
         }
     }
+
+    /// Scroll the label across the key when it does not fit.
+    ///
+    /// The label is drawn on a single line and moves by `speed` pixels
+    /// per second. Labels that fit are drawn as usual.
+    pub fn with_marquee(self, speed: f32) -> Self {
+        Button {
+            marquee: Some(speed),
+            ..self
+        }
+    }
+
+    /// Get the scrolling speed of the label, if it scrolls.
+    pub fn get_marquee(&self) -> Option<f32> {
+        self.marquee
+    }
+
+    /// Update the layout of the label and the icon of the button.
+    pub fn with_layout(self, layout: Layout) -> Self {
+        Button {
+            layout: Some(layout),
+            ..self
+        }
+    }
+
+    /// Get the layout of the button, if it overrides the render config.
+    pub fn get_layout(&self) -> Option<Layout> {
+        self.layout
+    }
+
+    /// Update the fonts the label of the button is drawn with.
+    pub fn with_font(self, font: FontChain) -> Self {
+        Button {
+            font: Some(font),
+            ..self
+        }
+    }
+
+    /// Update the font weight of the label of the button.
+    ///
+    /// The fonts for the weight are taken from the render config, and the
+    /// label is drawn with the default fonts if the config has none.
+    pub fn with_font_weight(self, font_weight: FontWeight) -> Self {
+        Button {
+            font_weight: Some(font_weight),
+            ..self
+        }
+    }
+
+    /// Get the fonts of the button, if they override the render config.
+    pub fn get_font(&self) -> Option<&FontChain> {
+        self.font.as_ref()
+    }
+
+    /// Get the font weight of the button, if it overrides the render config.
+    pub fn get_font_weight(&self) -> Option<FontWeight> {
+        self.font_weight
+    }
+}
+
+impl Button {
+    /// Convert the button to a renderable button.
+    ///
+    /// The colors are taken from the button's own theme if it has one,
+    /// or from the given theme otherwise.
+    pub(crate) fn to_render_button(&self, theme: &Theme) -> crate::button::Button {
+        let (background_color, foreground_color) = self.colors(theme);
+        if let Some(image) = &self.image {
+            return crate::button::Button::Image {
+                image: image.clone(),
+                text: self.text.to_string(),
+                background: background_color,
+                foreground: foreground_color,
+                tint: (self.state != ButtonState::Default).then_some(background_color),
+            };
+        }
+        match &self.icon {
+            Some(icon) => crate::button::Button::IconWithText {
+                svg_data: icon.clone(),
+                text: self.text.to_string(),
+                background: background_color,
+                foreground: foreground_color,
+            },
+            None => crate::button::Button::Text {
+                text: self.text.to_string(),
+                background: background_color,
+                foreground: foreground_color,
+            },
+        }
+    }
+
+    /// Convert the button to a renderable button with its label
+    /// scrolled by the given number of pixels.
+    pub(crate) fn to_marquee_render_button(
+        &self,
+        theme: &Theme,
+        offset: u32,
+    ) -> crate::button::Button {
+        let (background, foreground) = self.colors(theme);
+        crate::button::Button::Marquee {
+            svg_data: self.icon.clone(),
+            text: self.text.to_string(),
+            offset,
+            background,
+            foreground,
+        }
+    }
+
+    /// Get the scrolling speed of the label and the distance it scrolls
+    /// before it repeats.
+    ///
+    /// Returns `None` if the label does not scroll with the given
+    /// configuration.
+    pub(crate) fn marquee_cycle(&self, config: &RenderConfig) -> Option<(f32, u32)> {
+        let speed = self
+            .marquee
+            .filter(|speed| *speed > 0.0 && self.image.is_none())?;
+        Some((speed, marquee_cycle(&self.text, config)?))
+    }
+
+    /// Get the render configuration for the button.
+    ///
+    /// The layout and fonts are taken from the button if it has them, or
+    /// from the given configuration otherwise.
+    pub(crate) fn render_config(&self, config: &RenderConfig) -> RenderConfig {
+        let mut config = config.clone();
+        if let Some(layout) = self.layout {
+            config = config.with_layout(layout);
+        }
+        let weight_font = self
+            .font_weight
+            .and_then(|weight| config.weight_font(weight))
+            .cloned();
+        if let Some(font) = self.font.clone().or(weight_font) {
+            config = config.with_font(font);
+        }
+        config
+    }
+
+    /// Get the background and foreground colors of the button.
+    fn colors(&self, theme: &Theme) -> (Color, Color) {
+        let theme = self.theme.as_ref().unwrap_or(theme);
+        let background_color = match self.state {
+            ButtonState::Default => theme.background,
+            ButtonState::Active => theme.active_background,
+            ButtonState::Inactive => theme.inactive_background,
+            ButtonState::Error => theme.error_background,
+            ButtonState::Pressed => theme.pressed_background,
+        };
+        let foreground_color = match self.state {
+            ButtonState::Default => theme.foreground_color,
+            ButtonState::Active => theme.active_foreground_color,
+            ButtonState::Inactive => theme.foreground_color,
+            ButtonState::Error => theme.foreground_color,
+            ButtonState::Pressed => theme.active_foreground_color,
+        };
+        (background_color, foreground_color)
+    }
 }
 
 impl Default for Button {
@@ -130,8 +334,13 @@ impl Default for Button {
         Button {
             text: "".to_string(),
             icon: None,
+            image: None,
             state: ButtonState::Default,
             theme: None,
+            marquee: None,
+            layout: None,
+            font: None,
+            font_weight: None,
         }
     }
 }
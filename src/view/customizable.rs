@@ -4,11 +4,12 @@
 //! customizable views allow for programmatic creation of views with custom buttons.
 
 use std::{
+    collections::BTreeMap,
     future::Future,
     marker::PhantomData,
     pin::Pin,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicI64, Ordering},
         Arc,
     },
 };
@@ -16,9 +17,14 @@ use std::{
 use generic_array::{sequence::GenericSequence, GenericArray, ArrayLength};
 use tokio::sync::mpsc;
 
-use crate::{navigation::NavigationEntry, Theme};
+use crate::{
+    button::{FontChain, FontWeight, Icon, Layout, RasterImage},
+    input::MultiTap,
+    navigation::NavigationEntry,
+    Theme,
+};
 
-use super::{button::Button, button::ButtonState, matrix::ButtonMatrix, View};
+use super::{button::Button, button::ButtonState, matrix::ButtonMatrix, strip::Strip, View};
 
 type Matrix<W, H, C, N> = GenericArray<GenericArray<Option<CustomizableViewButton<W, H, C, N>>, W>, H>;
 
@@ -35,6 +41,10 @@ where
 {
     /// The matrix of buttons.
     pub(crate) matrix: Matrix<W, H, C, N>,
+    /// The key combinations and their actions.
+    pub(crate) chords: Vec<(Vec<u8>, ClickAction<C>)>,
+    /// The encoders, by encoder index.
+    pub(crate) encoders: BTreeMap<u8, Box<dyn CustomEncoder<C>>>,
     /// Phantom data for the navigation type.
     pub(crate) _marker: PhantomData<N>,
 }
@@ -93,6 +103,84 @@ where
     /// This method is called when the button is clicked.
     /// It takes the application context.
     async fn click(&self, context: &C) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Handle a long press.
+    ///
+    /// This method is called when the button is held for at least the
+    /// long press threshold. The default implementation treats it as a click.
+    async fn long_press(&self, context: &C) -> Result<(), Box<dyn std::error::Error>> {
+        self.click(context).await
+    }
+
+    /// Get the longest multi-tap gesture handled by the button.
+    ///
+    /// Clicks are delayed only for buttons that return `Some`.
+    /// The default implementation returns `None`.
+    fn multi_tap_support(&self) -> Option<MultiTap> {
+        None
+    }
+
+    /// Check whether the button repeats while held.
+    ///
+    /// The default implementation returns `false`.
+    fn repeats(&self) -> bool {
+        false
+    }
+
+    /// Handle a multi-tap gesture.
+    ///
+    /// This method is called when the button is tapped several times in
+    /// quick succession. The default implementation treats every tap as
+    /// a click.
+    async fn multi_tap(&self, context: &C, tap: MultiTap) -> Result<(), Box<dyn std::error::Error>> {
+        for _ in 0..tap.count() {
+            self.click(context).await?;
+        }
+        Ok(())
+    }
+}
+
+/// A trait for custom encoders.
+///
+/// This trait is implemented by types that handle an encoder, such as
+/// a dial on the Stream Deck+, in a customizable view.
+#[async_trait::async_trait]
+pub trait CustomEncoder<C>: Send + Sync + 'static
+where
+    C: Send + Clone + Sync + 'static,
+{
+    /// Fetch state for the encoder.
+    ///
+    /// This method fetches the state for the encoder.
+    /// It takes the application context.
+    async fn fetch(&self, context: &C) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Handle an encoder twist.
+    ///
+    /// Positive ticks are clockwise, negative ticks are counter-clockwise.
+    async fn twist(&self, context: &C, ticks: i8) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Handle an encoder press.
+    ///
+    /// The default implementation does nothing.
+    async fn press(&self, _context: &C) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    /// Handle an encoder release.
+    ///
+    /// The default implementation does nothing.
+    async fn release(&self, _context: &C) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    /// Get the touch strip segment shown above the encoder.
+    ///
+    /// The default implementation returns `None`, which leaves the
+    /// segment empty.
+    fn get_strip(&self) -> Option<Button> {
+        None
+    }
 }
 
 /// A future that returns a boolean.
@@ -116,6 +204,19 @@ pub type PushFuture =
 /// A function that returns a push future.
 pub type PushFunction<C> = Arc<Box<dyn Fn(&C, bool) -> PushFuture + Send + Sync>>;
 
+/// A future that returns an integer value.
+pub type FetchValueFuture =
+    Pin<Box<dyn Future<Output = Result<i64, Box<dyn std::error::Error>>> + Send + Sync>>;
+
+/// A function that returns a fetch value future.
+pub type FetchValueFunction<C> = Arc<Box<dyn Fn(&C) -> FetchValueFuture + Send + Sync>>;
+
+/// A function that pushes an integer value.
+pub type PushValueFunction<C> = Arc<Box<dyn Fn(&C, i64) -> PushFuture + Send + Sync>>;
+
+/// A function that formats an integer value for display.
+pub type FormatFunction = Arc<dyn Fn(i64) -> String + Send + Sync>;
+
 /// A toggle button.
 ///
 /// This struct represents a toggle button in a customizable view.
@@ -134,6 +235,8 @@ where
     pub(crate) active_button: Button,
     /// The current active state.
     pub(crate) active: AtomicBool,
+    /// The function to call when long pressed.
+    pub(crate) push_long_press: Option<ClickAction<C>>,
 }
 
 /// A click button.
@@ -146,6 +249,45 @@ where
 {
     /// The function to call when clicked.
     pub(crate) push_click: ClickAction<C>,
+    /// The function to call when long pressed.
+    pub(crate) push_long_press: Option<ClickAction<C>>,
+    /// The function to call when double tapped.
+    pub(crate) push_double_tap: Option<ClickAction<C>>,
+    /// The function to call when triple tapped.
+    pub(crate) push_triple_tap: Option<ClickAction<C>>,
+    /// Whether the button repeats while held.
+    pub(crate) repeat: bool,
+    /// The button to display.
+    pub(crate) button: Button,
+}
+
+/// A dial value.
+///
+/// This struct represents an integer value bound to an encoder, such as
+/// a volume or brightness level. Twisting the encoder changes the value
+/// within a range. It can also be placed on a key, which shows the value.
+/// Clones share the same value, so the same dial value can be set as both
+/// an encoder and a button of a customizable view.
+pub struct DialValue<C>
+where
+    C: Send + Clone + Sync + 'static,
+{
+    /// The function to fetch the value.
+    pub(crate) fetch_value: FetchValueFunction<C>,
+    /// The function to push the value.
+    pub(crate) push_value: PushValueFunction<C>,
+    /// The function to call when the encoder is pressed or the key is clicked.
+    pub(crate) push_press: Option<ClickAction<C>>,
+    /// The function to format the value.
+    pub(crate) format: FormatFunction,
+    /// The current value.
+    pub(crate) value: Arc<AtomicI64>,
+    /// The smallest value.
+    pub(crate) min: i64,
+    /// The largest value.
+    pub(crate) max: i64,
+    /// How much the value changes per tick.
+    pub(crate) step: i64,
     /// The button to display.
     pub(crate) button: Button,
 }
@@ -157,35 +299,144 @@ where
     /// Create a new click button.
     ///
     /// This method creates a new click button with the given text,
-    /// icon, and action.
-    pub fn new<A, F, S>(text: S, icon: Option<&'static str>, action: A) -> Self
+    /// icon, and action. The icon can be an SVG compiled into the binary
+    /// or an `Icon` loaded at runtime; pass `None::<Icon>` for no icon.
+    pub fn new<A, F, S>(text: S, icon: Option<impl Into<Icon>>, action: A) -> Self
     where
         F: Future<Output = Result<(), Box<dyn std::error::Error>>> + Send + Sync + 'static,
         A: Fn(C) -> F + Send + Sync + Clone + 'static,
         S: Into<String>
     {
         ClickButton {
-            push_click: Arc::new(Box::new(move |ctx| {
-                let action = action.clone();
-                let ctx = ctx.clone();
-                Box::pin(async move { action(ctx).await })
-            })),
-            button: Button {
-                text: text.into(),
-                icon,
-                state: ButtonState::Default,
-                theme: None,
-            },
+            push_click: click_action(action),
+            push_long_press: None,
+            push_double_tap: None,
+            push_triple_tap: None,
+            repeat: false,
+            button: Button::new(text.into(), icon, ButtonState::Default),
         }
     }
 
     pub fn with_theme(self, theme: Theme) -> Self {
-        let ClickButton { push_click, button } = self;
         ClickButton {
-            push_click,
-            button: button.with_theme(theme),
+            button: self.button.with_theme(theme),
+            ..self
+        }
+    }
+
+    /// Set the icon of the button, replacing the one given to `new`.
+    pub fn with_icon(self, icon: impl Into<Icon>) -> Self {
+        ClickButton {
+            button: self.button.updated_icon(icon),
+            ..self
+        }
+    }
+
+    /// Set the raster image of the button, drawn instead of the icon.
+    pub fn with_image(self, image: impl Into<RasterImage>) -> Self {
+        ClickButton {
+            button: self.button.updated_image(image),
+            ..self
+        }
+    }
+
+    /// Set the layout of the label and the icon.
+    pub fn with_layout(self, layout: Layout) -> Self {
+        ClickButton {
+            button: self.button.with_layout(layout),
+            ..self
+        }
+    }
+
+    /// Set the fonts the label is drawn with.
+    pub fn with_font(self, font: FontChain) -> Self {
+        ClickButton {
+            button: self.button.with_font(font),
+            ..self
+        }
+    }
+
+    /// Set the font weight of the label.
+    pub fn with_font_weight(self, font_weight: FontWeight) -> Self {
+        ClickButton {
+            button: self.button.with_font_weight(font_weight),
+            ..self
+        }
+    }
+
+    /// Set the long press action.
+    ///
+    /// This method sets an action that is performed instead of the click
+    /// action when the button is held for at least the long press threshold.
+    pub fn on_long_press<A, F>(self, action: A) -> Self
+    where
+        F: Future<Output = Result<(), Box<dyn std::error::Error>>> + Send + Sync + 'static,
+        A: Fn(C) -> F + Send + Sync + Clone + 'static,
+    {
+        ClickButton {
+            push_long_press: Some(click_action(action)),
+            ..self
+        }
+    }
+
+    /// Set the double tap action.
+    ///
+    /// This method sets an action that is performed when the button is
+    /// tapped twice in quick succession. Single clicks on the button are
+    /// delayed until the multi-tap window has passed.
+    pub fn on_double_tap<A, F>(self, action: A) -> Self
+    where
+        F: Future<Output = Result<(), Box<dyn std::error::Error>>> + Send + Sync + 'static,
+        A: Fn(C) -> F + Send + Sync + Clone + 'static,
+    {
+        ClickButton {
+            push_double_tap: Some(click_action(action)),
+            ..self
         }
     }
+
+    /// Set the triple tap action.
+    ///
+    /// This method sets an action that is performed when the button is
+    /// tapped three times in quick succession. Single clicks on the button
+    /// are delayed until the multi-tap window has passed.
+    pub fn on_triple_tap<A, F>(self, action: A) -> Self
+    where
+        F: Future<Output = Result<(), Box<dyn std::error::Error>>> + Send + Sync + 'static,
+        A: Fn(C) -> F + Send + Sync + Clone + 'static,
+    {
+        ClickButton {
+            push_triple_tap: Some(click_action(action)),
+            ..self
+        }
+    }
+
+    /// Make the button repeat while held.
+    ///
+    /// A repeating button performs its action as soon as it is pressed,
+    /// and then again at the rate set in the input configuration until
+    /// it is released. This is useful for steppers such as volume or
+    /// brightness controls.
+    pub fn repeating(self) -> Self {
+        ClickButton {
+            repeat: true,
+            ..self
+        }
+    }
+}
+
+/// Wrap an async closure into a click action.
+fn click_action<C, A, F>(action: A) -> ClickAction<C>
+where
+    C: Send + Clone + Sync + 'static,
+    F: Future<Output = Result<(), Box<dyn std::error::Error>>> + Send + Sync + 'static,
+    A: Fn(C) -> F + Send + Sync + Clone + 'static,
+{
+    Arc::new(Box::new(move |ctx| {
+        let action = action.clone();
+        let ctx = ctx.clone();
+        Box::pin(async move { action(ctx).await })
+    }))
 }
 
 impl<C> ToggleButton<C>
@@ -198,7 +449,7 @@ where
     /// icon, fetch function, and push function.
     pub fn new<FF, PF, F, P, S>(
         text: S,
-        icon: Option<&'static str>,
+        icon: Option<impl Into<Icon>>,
         fetch_active: F,
         push_active: P,
     ) -> Self
@@ -210,6 +461,7 @@ where
         S: Into<String>
     {
         let text = text.into();
+        let icon: Option<Icon> = icon.map(Into::into);
         ToggleButton {
             fetch_active: Arc::new(Box::new(move |ctx| {
                 let fetch_active = fetch_active.clone();
@@ -221,45 +473,106 @@ where
                 let ctx = ctx.clone();
                 Box::pin(async move { push_active(ctx, x).await })
             })),
-            button: Button {
-                text: text.clone(),
-                icon,
-                state: ButtonState::Default,
-                theme: None,
-            },
-            active_button: Button {
-                text,
-                icon,
-                state: ButtonState::Active,
-                theme: None,
-            },
+            button: Button::new(text.clone(), icon.clone(), ButtonState::Default),
+            active_button: Button::new(text, icon, ButtonState::Active),
             active: AtomicBool::new(false),
+            push_long_press: None,
         }
     }
 
     /// Set the active button.
     ///
     /// This method sets the button to display when active.
-    pub fn when_active<S: Into<String>>(self, text: S, icon: Option<&'static str>) -> Self {
+    pub fn when_active<S: Into<String>>(self, text: S, icon: Option<impl Into<Icon>>) -> Self {
         ToggleButton {
-            active_button: Button {
-                text: text.into(),
-                icon,
-                state: ButtonState::Active,
-                theme: None,
-            },
+            active_button: Button::new(text.into(), icon, ButtonState::Active),
             ..self
         }
     }
 
     pub fn with_theme(self, theme: Theme) -> Self {
-        let ToggleButton { fetch_active, push_active, button, active_button, active } = self;
         ToggleButton {
-            fetch_active,
-            push_active,
-            button: button.with_theme(theme),
-            active_button: active_button.with_theme(theme),
-            active,
+            button: self.button.with_theme(theme),
+            active_button: self.active_button.with_theme(theme),
+            ..self
+        }
+    }
+
+    /// Set the icon of the button, whether it is active or not.
+    pub fn with_icon(self, icon: impl Into<Icon>) -> Self {
+        let icon = icon.into();
+        ToggleButton {
+            button: self.button.updated_icon(icon.clone()),
+            active_button: self.active_button.updated_icon(icon),
+            ..self
+        }
+    }
+
+    /// Set the icon of the button when it is active.
+    pub fn with_active_icon(self, icon: impl Into<Icon>) -> Self {
+        ToggleButton {
+            active_button: self.active_button.updated_icon(icon),
+            ..self
+        }
+    }
+
+    /// Set the raster image of the button, whether it is active or not.
+    pub fn with_image(self, image: impl Into<RasterImage>) -> Self {
+        let image = image.into();
+        ToggleButton {
+            button: self.button.updated_image(image.clone()),
+            active_button: self.active_button.updated_image(image),
+            ..self
+        }
+    }
+
+    /// Set the raster image of the button when it is active.
+    pub fn with_active_image(self, image: impl Into<RasterImage>) -> Self {
+        ToggleButton {
+            active_button: self.active_button.updated_image(image),
+            ..self
+        }
+    }
+
+    /// Set the layout of the label and the icon.
+    pub fn with_layout(self, layout: Layout) -> Self {
+        ToggleButton {
+            button: self.button.with_layout(layout),
+            active_button: self.active_button.with_layout(layout),
+            ..self
+        }
+    }
+
+    /// Set the fonts the label is drawn with.
+    pub fn with_font(self, font: FontChain) -> Self {
+        ToggleButton {
+            button: self.button.with_font(font.clone()),
+            active_button: self.active_button.with_font(font),
+            ..self
+        }
+    }
+
+    /// Set the font weight of the label.
+    pub fn with_font_weight(self, font_weight: FontWeight) -> Self {
+        ToggleButton {
+            button: self.button.with_font_weight(font_weight),
+            active_button: self.active_button.with_font_weight(font_weight),
+            ..self
+        }
+    }
+
+    /// Set the long press action.
+    ///
+    /// This method sets an action that is performed instead of toggling
+    /// when the button is held for at least the long press threshold.
+    pub fn on_long_press<A, F>(self, action: A) -> Self
+    where
+        F: Future<Output = Result<(), Box<dyn std::error::Error>>> + Send + Sync + 'static,
+        A: Fn(C) -> F + Send + Sync + Clone + 'static,
+    {
+        ToggleButton {
+            push_long_press: Some(click_action(action)),
+            ..self
         }
     }
 }
@@ -289,6 +602,236 @@ where
         self.active.store(!current_state, Ordering::SeqCst);
         Ok(())
     }
+
+    async fn long_press(&self, context: &C) -> Result<(), Box<dyn std::error::Error>> {
+        match &self.push_long_press {
+            Some(push_long_press) => push_long_press(context).await,
+            None => self.click(context).await,
+        }
+    }
+}
+
+impl<C> DialValue<C>
+where
+    C: Send + Clone + Sync + 'static,
+{
+    /// Create a new dial value.
+    ///
+    /// This method creates a new dial value with the given text, icon,
+    /// fetch function, and push function. The value ranges from 0 to 100
+    /// in steps of 1 by default.
+    pub fn new<FF, PF, F, P, S>(
+        text: S,
+        icon: Option<impl Into<Icon>>,
+        fetch_value: F,
+        push_value: P,
+    ) -> Self
+    where
+        FF: Future<Output = Result<i64, Box<dyn std::error::Error>>> + Send + Sync + 'static,
+        PF: Future<Output = Result<(), Box<dyn std::error::Error>>> + Send + Sync + 'static,
+        F: Fn(C) -> FF + Send + Sync + Clone + 'static,
+        P: Fn(C, i64) -> PF + Send + Sync + Clone + 'static,
+        S: Into<String>,
+    {
+        DialValue {
+            fetch_value: Arc::new(Box::new(move |ctx| {
+                let fetch_value = fetch_value.clone();
+                let ctx = ctx.clone();
+                Box::pin(async move { fetch_value(ctx).await })
+            })),
+            push_value: Arc::new(Box::new(move |ctx, value| {
+                let push_value = push_value.clone();
+                let ctx = ctx.clone();
+                Box::pin(async move { push_value(ctx, value).await })
+            })),
+            push_press: None,
+            format: Arc::new(|value| value.to_string()),
+            value: Arc::new(AtomicI64::new(0)),
+            min: 0,
+            max: 100,
+            step: 1,
+            button: Button::new(text.into(), icon, ButtonState::Default),
+        }
+    }
+
+    /// Set the range of the value.
+    ///
+    /// The bounds can be given in either order.
+    pub fn with_range(self, min: i64, max: i64) -> Self {
+        DialValue {
+            min: min.min(max),
+            max: min.max(max),
+            ..self
+        }
+    }
+
+    /// Set how much the value changes per tick.
+    pub fn with_step(self, step: i64) -> Self {
+        DialValue { step, ..self }
+    }
+
+    /// Set how the value is shown on the key.
+    ///
+    /// The key shows the text of the dial value followed by the
+    /// formatted value.
+    pub fn with_format<F>(self, format: F) -> Self
+    where
+        F: Fn(i64) -> String + Send + Sync + 'static,
+    {
+        DialValue {
+            format: Arc::new(format),
+            ..self
+        }
+    }
+
+    pub fn with_theme(self, theme: Theme) -> Self {
+        DialValue {
+            button: self.button.with_theme(theme),
+            ..self
+        }
+    }
+
+    /// Set the icon of the key, replacing the one given to `new`.
+    pub fn with_icon(self, icon: impl Into<Icon>) -> Self {
+        DialValue {
+            button: self.button.updated_icon(icon),
+            ..self
+        }
+    }
+
+    /// Set the raster image of the key, drawn instead of the icon.
+    pub fn with_image(self, image: impl Into<RasterImage>) -> Self {
+        DialValue {
+            button: self.button.updated_image(image),
+            ..self
+        }
+    }
+
+    /// Set the layout of the label and the icon.
+    pub fn with_layout(self, layout: Layout) -> Self {
+        DialValue {
+            button: self.button.with_layout(layout),
+            ..self
+        }
+    }
+
+    /// Set the fonts the label is drawn with.
+    pub fn with_font(self, font: FontChain) -> Self {
+        DialValue {
+            button: self.button.with_font(font),
+            ..self
+        }
+    }
+
+    /// Set the font weight of the label.
+    pub fn with_font_weight(self, font_weight: FontWeight) -> Self {
+        DialValue {
+            button: self.button.with_font_weight(font_weight),
+            ..self
+        }
+    }
+
+    /// Set the press action.
+    ///
+    /// This method sets an action that is performed when the encoder is
+    /// pressed or the key showing the value is clicked, for example to
+    /// mute or reset the value.
+    pub fn on_press<A, F>(self, action: A) -> Self
+    where
+        F: Future<Output = Result<(), Box<dyn std::error::Error>>> + Send + Sync + 'static,
+        A: Fn(C) -> F + Send + Sync + Clone + 'static,
+    {
+        DialValue {
+            push_press: Some(click_action(action)),
+            ..self
+        }
+    }
+
+    /// Get the current value.
+    pub fn get_value(&self) -> i64 {
+        self.value.load(Ordering::SeqCst)
+    }
+}
+
+impl<C> Clone for DialValue<C>
+where
+    C: Send + Clone + Sync + 'static,
+{
+    fn clone(&self) -> Self {
+        DialValue {
+            fetch_value: self.fetch_value.clone(),
+            push_value: self.push_value.clone(),
+            push_press: self.push_press.clone(),
+            format: self.format.clone(),
+            value: self.value.clone(),
+            min: self.min,
+            max: self.max,
+            step: self.step,
+            button: self.button.clone(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<C> CustomEncoder<C> for DialValue<C>
+where
+    C: Send + Clone + Sync + 'static,
+{
+    async fn fetch(&self, context: &C) -> Result<(), Box<dyn std::error::Error>> {
+        let new_value = (self.fetch_value)(context).await;
+        self.value.store(new_value?, Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn twist(&self, context: &C, ticks: i8) -> Result<(), Box<dyn std::error::Error>> {
+        let current_value = self.value.load(Ordering::SeqCst);
+        let new_value = current_value
+            .saturating_add(self.step.saturating_mul(ticks as i64))
+            .clamp(self.min, self.max);
+        if new_value != current_value {
+            (self.push_value)(context, new_value).await?;
+            self.value.store(new_value, Ordering::SeqCst);
+        }
+        Ok(())
+    }
+
+    async fn press(&self, context: &C) -> Result<(), Box<dyn std::error::Error>> {
+        match &self.push_press {
+            Some(push_press) => push_press(context).await,
+            None => Ok(()),
+        }
+    }
+
+    fn get_strip(&self) -> Option<Button> {
+        Some(CustomButton::get_state(self))
+    }
+}
+
+#[async_trait::async_trait]
+impl<C> CustomButton<C> for DialValue<C>
+where
+    C: Send + Clone + Sync + 'static,
+{
+    fn get_state(&self) -> Button {
+        let value = (self.format)(self.value.load(Ordering::SeqCst));
+        let text = if self.button.text.is_empty() {
+            value
+        } else {
+            format!("{} {}", self.button.text, value)
+        };
+        Button {
+            text,
+            ..self.button.clone()
+        }
+    }
+
+    async fn fetch(&self, context: &C) -> Result<(), Box<dyn std::error::Error>> {
+        CustomEncoder::fetch(self, context).await
+    }
+
+    async fn click(&self, context: &C) -> Result<(), Box<dyn std::error::Error>> {
+        CustomEncoder::press(self, context).await
+    }
 }
 
 #[async_trait::async_trait]
@@ -308,6 +851,43 @@ where
         (self.push_click)(context).await?;
         Ok(())
     }
+
+    async fn long_press(&self, context: &C) -> Result<(), Box<dyn std::error::Error>> {
+        match &self.push_long_press {
+            Some(push_long_press) => push_long_press(context).await,
+            None => self.click(context).await,
+        }
+    }
+
+    fn repeats(&self) -> bool {
+        self.repeat
+    }
+
+    fn multi_tap_support(&self) -> Option<MultiTap> {
+        if self.push_triple_tap.is_some() {
+            Some(MultiTap::Triple)
+        } else if self.push_double_tap.is_some() {
+            Some(MultiTap::Double)
+        } else {
+            None
+        }
+    }
+
+    async fn multi_tap(&self, context: &C, tap: MultiTap) -> Result<(), Box<dyn std::error::Error>> {
+        let action = match tap {
+            MultiTap::Double => &self.push_double_tap,
+            MultiTap::Triple => &self.push_triple_tap,
+        };
+        match action {
+            Some(action) => action(context).await,
+            None => {
+                for _ in 0..tap.count() {
+                    self.click(context).await?;
+                }
+                Ok(())
+            }
+        }
+    }
 }
 
 impl<W, H, C, N> Default for CustomizableView<W, H, C, N>
@@ -333,6 +913,8 @@ where
     pub fn new() -> Self {
         CustomizableView {
             matrix: GenericArray::generate(|_| GenericArray::generate(|_| None)),
+            chords: Vec::new(),
+            encoders: BTreeMap::new(),
             _marker: PhantomData,
         }
     }
@@ -366,17 +948,31 @@ where
         y: usize,
         navigation: N,
         text: S,
-        icon: Option<&'static str>,
+        icon: Option<impl Into<Icon>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.set_navigation_button(
+            x,
+            y,
+            navigation,
+            Button::new(text.into(), icon, ButtonState::Default),
+        )
+    }
+
+    /// Set a navigation button drawn as the given button.
+    ///
+    /// This method sets a navigation button at the given coordinates,
+    /// with its own icon, theme, layout and fonts.
+    pub fn set_navigation_button(
+        &mut self,
+        x: usize,
+        y: usize,
+        navigation: N,
+        button: Button,
     ) -> Result<(), Box<dyn std::error::Error>> {
         if x < W::to_usize() && y < H::to_usize() {
             self.matrix[y][x] = Some(CustomizableViewButton::Navigation {
                 navigation,
-                button: Button {
-                    text: text.into(),
-                    icon,
-                    state: ButtonState::Default,
-                    theme: None,
-                },
+                button,
                 _marker: PhantomData,
             });
             Ok(())
@@ -388,6 +984,57 @@ where
         }
     }
 
+    /// Set the encoder at the given index.
+    ///
+    /// This method sets a custom encoder, such as a dial on the
+    /// Stream Deck+, at the given encoder index.
+    pub fn set_encoder(&mut self, index: u8, encoder: impl CustomEncoder<C>) {
+        self.encoders.insert(index, Box::new(encoder));
+    }
+
+    /// Remove the encoder at the given index.
+    pub fn remove_encoder(&mut self, index: u8) {
+        self.encoders.remove(&index);
+    }
+
+    /// Add a chord.
+    ///
+    /// This method sets an action that is performed when all the given
+    /// buttons are held at once. The buttons are given by index, which is
+    /// calculated as `y * width + x`. Releasing the buttons of a chord does
+    /// not click them.
+    pub fn add_chord<A, F>(&mut self, keys: &[u8], action: A) -> Result<(), Box<dyn std::error::Error>>
+    where
+        F: Future<Output = Result<(), Box<dyn std::error::Error>>> + Send + Sync + 'static,
+        A: Fn(C) -> F + Send + Sync + Clone + 'static,
+    {
+        if keys.len() < 2 {
+            return Err(Box::new(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "A chord needs at least two buttons",
+            )));
+        }
+        for key in keys {
+            Self::coordinates(*key)?;
+        }
+        self.chords.push((keys.to_vec(), click_action(action)));
+        Ok(())
+    }
+
+    /// Get the coordinates of the button at the given index.
+    ///
+    /// The index is calculated as `y * width + x`.
+    fn coordinates(index: u8) -> Result<(usize, usize), Box<dyn std::error::Error>> {
+        if (index as usize) < W::to_usize() * H::to_usize() {
+            Ok((index as usize % W::to_usize(), index as usize / W::to_usize()))
+        } else {
+            Err(Box::new(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Button index out of bounds",
+            )))
+        }
+    }
+
     /// Remove a button at the given coordinates.
     ///
     /// This method removes the button at the given coordinates.
@@ -434,25 +1081,138 @@ where
         index: u8,
         navigation: Arc<mpsc::Sender<N>>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        if (index as usize) < W::to_usize() * H::to_usize() {
-            let x = index % W::to_u8();
-            let y = index / W::to_u8();
-            if let Some(button) = &self.matrix[y as usize][x as usize] {
-                match button {
-                    CustomizableViewButton::Navigation { navigation: nav, .. } => {
-                        navigation.send(nav.clone()).await?;
-                    }
-                    CustomizableViewButton::Button(button) => {
-                        button.click(context).await?;
-                    }
+        let (x, y) = Self::coordinates(index)?;
+        if let Some(button) = &self.matrix[y][x] {
+            match button {
+                CustomizableViewButton::Navigation { navigation: nav, .. } => {
+                    navigation.send(nav.clone()).await?;
+                }
+                CustomizableViewButton::Button(button) => {
+                    button.click(context).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn on_long_press(
+        &self,
+        context: &C,
+        index: u8,
+        navigation: Arc<mpsc::Sender<N>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (x, y) = Self::coordinates(index)?;
+        if let Some(button) = &self.matrix[y][x] {
+            match button {
+                CustomizableViewButton::Navigation { navigation: nav, .. } => {
+                    navigation.send(nav.clone()).await?;
+                }
+                CustomizableViewButton::Button(button) => {
+                    button.long_press(context).await?;
                 }
             }
-            Ok(())
-        } else {
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                "Button index out of bounds",
-            )));
+        }
+        Ok(())
+    }
+
+    async fn repeats(&self, index: u8) -> bool {
+        let Ok((x, y)) = Self::coordinates(index) else {
+            return false;
+        };
+        match &self.matrix[y][x] {
+            Some(CustomizableViewButton::Button(button)) => button.repeats(),
+            _ => false,
+        }
+    }
+
+    async fn multi_tap_support(&self, index: u8) -> Option<MultiTap> {
+        let (x, y) = Self::coordinates(index).ok()?;
+        match &self.matrix[y][x] {
+            Some(CustomizableViewButton::Button(button)) => button.multi_tap_support(),
+            _ => None,
+        }
+    }
+
+    async fn on_multi_tap(
+        &self,
+        context: &C,
+        index: u8,
+        tap: MultiTap,
+        navigation: Arc<mpsc::Sender<N>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (x, y) = Self::coordinates(index)?;
+        match &self.matrix[y][x] {
+            Some(CustomizableViewButton::Button(button)) => button.multi_tap(context, tap).await,
+            _ => self.on_click(context, index, navigation).await,
+        }
+    }
+
+    async fn render_strip(&self) -> Result<Option<Strip>, Box<dyn std::error::Error>> {
+        let segments: Vec<(u8, Button)> = self
+            .encoders
+            .iter()
+            .filter_map(|(index, encoder)| Some((*index, encoder.get_strip()?)))
+            .collect();
+        let Some((last, _)) = segments.last() else {
+            return Ok(None);
+        };
+        let mut strip = vec![Button::text(String::new()); *last as usize + 1];
+        for (index, button) in segments {
+            strip[index as usize] = button;
+        }
+        Ok(Some(Strip::Segments(strip)))
+    }
+
+    async fn chords(&self) -> Vec<Vec<u8>> {
+        self.chords.iter().map(|(keys, _)| keys.clone()).collect()
+    }
+
+    async fn on_chord(
+        &self,
+        context: &C,
+        keys: &[u8],
+        _navigation: Arc<mpsc::Sender<N>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match self.chords.iter().find(|(chord, _)| chord == keys) {
+            Some((_, action)) => action(context).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn on_encoder_twist(
+        &self,
+        context: &C,
+        encoder: u8,
+        ticks: i8,
+        _navigation: Arc<mpsc::Sender<N>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match self.encoders.get(&encoder) {
+            Some(encoder) => encoder.twist(context, ticks).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn on_encoder_press(
+        &self,
+        context: &C,
+        encoder: u8,
+        _navigation: Arc<mpsc::Sender<N>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match self.encoders.get(&encoder) {
+            Some(encoder) => encoder.press(context).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn on_encoder_release(
+        &self,
+        context: &C,
+        encoder: u8,
+        _navigation: Arc<mpsc::Sender<N>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match self.encoders.get(&encoder) {
+            Some(encoder) => encoder.release(context).await,
+            None => Ok(()),
         }
     }
 
@@ -469,6 +1229,64 @@ where
                 }
             }
         }
+        for encoder in self.encoders.values() {
+            encoder.fetch(context).await?;
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Mutex;
+
+    type Log = Arc<Mutex<Vec<i64>>>;
+
+    fn dial(value: i64) -> DialValue<Log> {
+        DialValue::new(
+            "Volume",
+            None::<Icon>,
+            move |_: Log| async move { Ok(value) },
+            |log: Log, value| async move {
+                log.lock().unwrap().push(value);
+                Ok(())
+            },
+        )
+    }
+
+    #[test]
+    fn constructors_take_runtime_icons() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24"><rect width="24" height="24"/></svg>"#;
+        let icon = Icon::from_svg(svg.to_string()).unwrap();
+
+        let click = ClickButton::new("Click", Some(icon.clone()), |_: Log| async { Ok(()) });
+        assert_eq!(click.get_state().get_icon(), Some(&icon));
+        let toggle = ToggleButton::new(
+            "Toggle",
+            Some(svg),
+            |_: Log| async { Ok(false) },
+            |_: Log, _| async { Ok(()) },
+        )
+        .when_active("Active", Some(icon.clone()));
+        assert_eq!(toggle.get_state().get_icon(), Some(&Icon::from(svg)));
+        assert_eq!(toggle.active_button.get_icon(), Some(&icon));
+    }
+
+    #[tokio::test]
+    async fn dial_is_clamped_to_its_range() {
+        let log = Log::default();
+        let dial = dial(5).with_range(10, 0).with_step(2);
+        CustomEncoder::fetch(&dial, &log).await.unwrap();
+
+        dial.twist(&log, 4).await.unwrap();
+        assert_eq!(dial.get_value(), 10);
+        dial.twist(&log, 1).await.unwrap();
+        dial.twist(&log, -100).await.unwrap();
+        assert_eq!(dial.get_value(), 0);
+        dial.twist(&log, -1).await.unwrap();
+
+        assert_eq!(*log.lock().unwrap(), vec![10, 0]);
+    }
+}
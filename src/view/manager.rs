@@ -2,19 +2,39 @@
 //!
 //! This module provides a display manager for the view system.
 
-use std::{marker::PhantomData, sync::Arc};
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
 
-use elgato_streamdeck::AsyncStreamDeck;
 use generic_array::ArrayLength;
-use tokio::sync::{mpsc, RwLock};
+use image::DynamicImage;
+use tokio::{
+    sync::{mpsc, RwLock},
+    task::JoinSet,
+};
 
 use crate::{
     button::{render_button, RenderConfig},
+    device::DeckDevice,
+    error::Error,
+    input::{InputConfig, MultiTap, TouchEvent},
     navigation::NavigationEntry,
+    screenshot::{render_screenshot, ScreenshotLayout},
     theme::Theme,
 };
 
-use super::{button::ButtonState, matrix::ButtonMatrix, View};
+use super::{
+    button::{Button, ButtonState},
+    matrix::ButtonMatrix,
+    strip::{render_strip, Strip},
+    View,
+};
 
 /// A display manager for the view system.
 ///
@@ -28,10 +48,12 @@ where
 {
     /// The render configuration.
     pub(crate) config: RenderConfig,
+    /// The render configuration for touch strip segments.
+    pub(crate) strip_config: Option<RenderConfig>,
     /// The theme.
     pub(crate) theme: Theme,
     /// The Stream Deck.
-    pub(crate) deck: Arc<AsyncStreamDeck>,
+    pub(crate) deck: Arc<dyn DeckDevice>,
     /// The current view.
     pub(crate) view: RwLock<Box<dyn View<W, H, C, N>>>,
     /// Phantom data for the navigation type.
@@ -46,6 +68,56 @@ where
     pub(crate) context: C,
     /// Current navigation entry
     pub(crate) current_navigation: RwLock<N>,
+    /// The input configuration.
+    pub(crate) input: InputConfig,
+    /// The buttons that are currently held down.
+    pub(crate) presses: Mutex<HashMap<u8, KeyPress>>,
+    /// The number of navigations so far.
+    ///
+    /// Presses remember the generation of the view they started on, so
+    /// that releases on a different view are not taken as clicks.
+    pub(crate) generation: AtomicU64,
+    /// The buttons last uploaded to each key, indexed by button index.
+    pub(crate) uploaded: Mutex<Vec<Option<Button>>>,
+    /// The touch strip content last uploaded.
+    pub(crate) uploaded_strip: Mutex<Option<Strip>>,
+    /// The taps that are waiting for the multi-tap window to pass.
+    pub(crate) taps: Mutex<HashMap<u8, PendingTaps>>,
+    /// The keys whose labels are scrolling.
+    pub(crate) marquees: Mutex<HashMap<u8, MarqueeState>>,
+}
+
+/// The shortest time between two frames of a scrolling label.
+const MARQUEE_MIN_FRAME: Duration = Duration::from_millis(40);
+
+/// A button that is currently held down.
+pub(crate) struct KeyPress {
+    /// When the button was pressed.
+    pub(crate) pressed_at: Instant,
+    /// The generation of the view the button was pressed on.
+    pub(crate) generation: u64,
+    /// When the button repeats next, if it repeats while held.
+    pub(crate) next_repeat: Option<Instant>,
+    /// Whether the press completed a chord, so releasing it does nothing.
+    pub(crate) in_chord: bool,
+}
+
+/// Taps on a button that have not been delivered yet.
+pub(crate) struct PendingTaps {
+    /// The number of taps so far.
+    pub(crate) count: u8,
+    /// When the taps are delivered if no further tap arrives.
+    pub(crate) deadline: Instant,
+}
+
+/// A key whose label is scrolling.
+pub(crate) struct MarqueeState {
+    /// The scrolling label.
+    pub(crate) text: String,
+    /// When the label started scrolling.
+    pub(crate) started: Instant,
+    /// When the key is rendered next.
+    pub(crate) next_frame: Instant,
 }
 
 impl<N: NavigationEntry<W, H, C>, W, H, C> DisplayManager<N, W, H, C>
@@ -59,7 +131,7 @@ where
     /// This method creates a new display manager with the given
     /// Stream Deck, render configuration, theme, and context.
     pub async fn new(
-        deck: Arc<AsyncStreamDeck>,
+        deck: Arc<dyn DeckDevice>,
         config: RenderConfig,
         theme: Theme,
         context: C,
@@ -69,6 +141,7 @@ where
         Ok((
             Self {
                 config,
+                strip_config: None,
                 theme,
                 deck,
                 view: RwLock::new(N::default().get_view(context.clone()).await?),
@@ -78,20 +151,51 @@ where
                 navigation_sender: sender.clone(),
                 context,
                 current_navigation: RwLock::new(N::default()),
+                input: InputConfig::default(),
+                presses: Mutex::new(HashMap::new()),
+                generation: AtomicU64::new(0),
+                uploaded: Mutex::new(vec![None; W::to_usize() * H::to_usize()]),
+                uploaded_strip: Mutex::new(None),
+                taps: Mutex::new(HashMap::new()),
+                marquees: Mutex::new(HashMap::new()),
             },
             receiver,
         ))
     }
 
+    /// Set the input configuration.
+    ///
+    /// This method sets the timings used to recognize gestures.
+    pub fn with_input_config(self, input: InputConfig) -> Self {
+        Self { input, ..self }
+    }
+
+    /// Set the render configuration for touch strip segments.
+    ///
+    /// By default, segments are rendered with the key configuration,
+    /// resized to fill an equal part of the strip above each encoder.
+    pub fn with_strip_config(self, strip_config: RenderConfig) -> Self {
+        Self {
+            strip_config: Some(strip_config),
+            ..self
+        }
+    }
+
     /// Navigate to a new view.
     ///
     /// This method navigates to the view associated with the given
-    /// navigation entry.
+    /// navigation entry. Buttons that are still held from the previous
+    /// view stop repeating, and releasing them does not click the new view.
     pub async fn navigate_to(&self, navigation_entry: N) -> Result<(), Box<dyn std::error::Error>> {
         let mut view = self.view.write().await;
         let mut current_navigation = self.current_navigation.write().await;
         *view = navigation_entry.get_view(self.context.clone()).await?;
         *current_navigation = navigation_entry.clone();
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.taps.lock().unwrap().clear();
+        for press in self.presses.lock().unwrap().values_mut() {
+            press.next_repeat = None;
+        }
         Ok(())
     }
 
@@ -107,14 +211,50 @@ where
 
     /// Render the current view.
     ///
-    /// This method renders the current view to the Stream Deck.
+    /// This method renders the current view to the Stream Deck,
+    /// including the touch strip on devices that have one. Buttons that
+    /// are held down are shown as pressed.
     pub async fn render(&self) -> Result<(), Box<dyn std::error::Error>> {
         let view = self.view.read().await;
-        let button_matrix = view.render().await?;
+        let mut button_matrix = view.render().await?;
+        self.show_presses(&mut button_matrix)?;
         self.render_matrix(&button_matrix).await?;
+        self.render_touch_strip(view.as_ref()).await?;
         Ok(())
     }
 
+    /// Get the button matrix of the current view.
+    ///
+    /// This method renders the current view without sending it to the
+    /// Stream Deck.
+    pub async fn current_matrix(&self) -> Result<ButtonMatrix<W, H>, Box<dyn std::error::Error>> {
+        let view = self.view.read().await;
+        view.render().await
+    }
+
+    /// Get the button matrix of the current view as it is shown on the
+    /// Stream Deck, with the buttons held down shown as pressed.
+    pub(crate) async fn rendered_matrix(
+        &self,
+    ) -> Result<ButtonMatrix<W, H>, Box<dyn std::error::Error>> {
+        let mut button_matrix = self.current_matrix().await?;
+        self.show_presses(&mut button_matrix)?;
+        Ok(button_matrix)
+    }
+
+    /// Take a screenshot of the current view.
+    ///
+    /// This method renders the current view to a single image laid out
+    /// like the Stream Deck. Buttons held down are shown as pressed, and
+    /// scrolling labels are shown at their start.
+    pub async fn screenshot(
+        &self,
+        layout: &ScreenshotLayout,
+    ) -> Result<DynamicImage, Box<dyn std::error::Error>> {
+        let button_matrix = self.rendered_matrix().await?;
+        render_screenshot(&button_matrix, &self.config, &self.theme, layout)
+    }
+
     /// Fetch state for all buttons in the current view.
     ///
     /// This method fetches the state for all buttons in the current view.
@@ -129,81 +269,950 @@ where
 
     /// Render a button matrix to the Stream Deck.
     ///
-    /// This method renders the given button matrix to the Stream Deck.
+    /// This method renders and uploads only the buttons that changed since
+    /// the last upload, and flushes the Stream Deck once if any did. The
+    /// buttons are rendered in parallel on the blocking thread pool, so
+    /// rasterizing icons does not stall the async runtime. Scrolling
+    /// labels move on in `on_tick` instead.
     async fn render_matrix(
         &self,
         button_matrix: &ButtonMatrix<W, H>,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        for x in 0..W::to_usize() {
-            for y in 0..H::to_usize() {
-                let button = &button_matrix.buttons[y][x];
-                let button_index = (y * W::to_usize() + x) as u8;
-                let theme = button.theme.as_ref().unwrap_or(&self.theme);
-                let background_color = match button.state {
-                    ButtonState::Default => theme.background,
-                    ButtonState::Active => theme.active_background,
-                    ButtonState::Inactive => theme.inactive_background,
-                    ButtonState::Error => theme.error_background,
-                    ButtonState::Pressed => theme.pressed_background,
-                };
-                let foreground_color = match button.state {
-                    ButtonState::Default => theme.foreground_color,
-                    ButtonState::Active => theme.active_foreground_color,
-                    ButtonState::Inactive => theme.foreground_color,
-                    ButtonState::Error => theme.foreground_color,
-                    ButtonState::Pressed => theme.active_foreground_color,
-                };
-                let raw_button = match button.icon {
-                    Some(icon) => crate::button::Button::IconWithText {
-                        svg_data: icon,
-                        text: button.text.to_string(),
-                        background: background_color,
-                        foreground: foreground_color,
-                    },
-                    None => crate::button::Button::Text {
-                        text: button.text.to_string(),
-                        background: background_color,
-                        foreground: foreground_color,
-                    },
-                };
-                let image = render_button(&raw_button, &self.config)?;
-                self.deck.set_button_image(button_index, image).await?;
+        let changed: Vec<(u8, &Button)> = {
+            let uploaded = self.uploaded.lock().unwrap();
+            button_matrix
+                .buttons
+                .iter()
+                .flat_map(|row| row.iter())
+                .enumerate()
+                .filter(|(index, button)| uploaded[*index].as_ref() != Some(*button))
+                .map(|(index, button)| (index as u8, button))
+                .collect()
+        };
+        if changed.is_empty() {
+            return Ok(());
+        }
+        self.upload_buttons(&changed).await?;
+        let mut uploaded = self.uploaded.lock().unwrap();
+        for (button_index, button) in changed {
+            uploaded[button_index as usize] = Some(button.clone());
+        }
+        Ok(())
+    }
+
+    /// Render buttons in parallel and upload them with a single flush.
+    async fn upload_buttons(
+        &self,
+        buttons: &[(u8, &Button)],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let now = Instant::now();
+        let mut renders = JoinSet::new();
+        for (button_index, button) in buttons {
+            let button_index = *button_index;
+            let config = button.render_config(&self.config);
+            let raw_button = match self.marquee_offset(button_index, button, &config, now) {
+                Some(offset) => button.to_marquee_render_button(&self.theme, offset),
+                None => button.to_render_button(&self.theme),
+            };
+            renders.spawn_blocking(move || {
+                render_button(&raw_button, &config)
+                    .map(|image| (button_index, image))
+                    .map_err(|e| Error::RenderError(e.to_string()))
+            });
+        }
+        let mut images = HashMap::with_capacity(buttons.len());
+        while let Some(result) = renders.join_next().await {
+            let (button_index, image) = result??;
+            images.insert(button_index, image);
+        }
+        for (button_index, button) in buttons {
+            let image = images.remove(button_index).ok_or("Button was not rendered")?;
+            self.deck.set_button_info(*button_index, button).await?;
+            self.deck.set_button_image(*button_index, image).await?;
+        }
+        self.deck.flush().await?;
+        Ok(())
+    }
+
+    /// Get how far the label of a button is scrolled.
+    ///
+    /// This method starts or stops the scrolling timer of the key, and
+    /// returns `None` if the label does not scroll.
+    fn marquee_offset(
+        &self,
+        button_index: u8,
+        button: &Button,
+        config: &RenderConfig,
+        now: Instant,
+    ) -> Option<u32> {
+        let mut marquees = self.marquees.lock().unwrap();
+        let Some((speed, cycle)) = button.marquee_cycle(config) else {
+            marquees.remove(&button_index);
+            return None;
+        };
+        let state = marquees
+            .entry(button_index)
+            .and_modify(|state| {
+                if state.text != button.text {
+                    state.text = button.text.clone();
+                    state.started = now;
+                }
+            })
+            .or_insert_with(|| MarqueeState {
+                text: button.text.clone(),
+                started: now,
+                next_frame: now,
+            });
+        state.next_frame = now + Duration::from_secs_f32(1.0 / speed).max(MARQUEE_MIN_FRAME);
+        let scrolled = now.duration_since(state.started).as_secs_f32() * speed;
+        Some(scrolled as u32 % cycle)
+    }
+
+    /// Render the next frame of the scrolling labels that are due.
+    async fn render_marquees(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let now = Instant::now();
+        let due: Vec<(u8, Button)> = {
+            let marquees = self.marquees.lock().unwrap();
+            let uploaded = self.uploaded.lock().unwrap();
+            marquees
+                .iter()
+                .filter(|(_, state)| state.next_frame <= now)
+                .filter_map(|(button_index, _)| {
+                    let button = uploaded.get(*button_index as usize)?.clone()?;
+                    Some((*button_index, button))
+                })
+                .collect()
+        };
+        if due.is_empty() {
+            return Ok(());
+        }
+        let due: Vec<(u8, &Button)> = due.iter().map(|(index, button)| (*index, button)).collect();
+        self.upload_buttons(&due).await
+    }
+
+    /// Forget which buttons were uploaded.
+    ///
+    /// This method makes the next render upload every key, for example
+    /// after the Stream Deck was reset or reconnected.
+    pub fn invalidate(&self) {
+        self.uploaded
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .for_each(|button| *button = None);
+        *self.uploaded_strip.lock().unwrap() = None;
+    }
+
+    /// Show the buttons held down on the current view as pressed.
+    fn show_presses(
+        &self,
+        button_matrix: &mut ButtonMatrix<W, H>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let generation = self.generation.load(Ordering::SeqCst);
+        let held: Vec<u8> = self
+            .presses
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, press)| press.generation == generation)
+            .map(|(button, _)| *button)
+            .collect();
+        for button in held {
+            let button_index = button as usize;
+            if let Some(button) = button_matrix.get_button_by_index(button_index) {
+                let new_button = button.updated_state(ButtonState::Pressed);
+                button_matrix.set_button_by_index(button_index, new_button)?;
             }
-            self.deck.flush().await?;
         }
         Ok(())
     }
 
+    /// Render the touch strip of a view to the Stream Deck.
+    ///
+    /// This method does nothing on devices without a touch strip, if the
+    /// view does not draw one, or if the strip did not change since the
+    /// last upload.
+    async fn render_touch_strip(
+        &self,
+        view: &dyn View<W, H, C, N>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let Some((width, height)) = self.deck.lcd_size() else {
+            return Ok(());
+        };
+        let Some(strip) = view.render_strip().await? else {
+            return Ok(());
+        };
+        if self.uploaded_strip.lock().unwrap().as_ref() == Some(&strip) {
+            return Ok(());
+        }
+        let encoder_count = self.deck.encoder_count();
+        let strip_config = match &self.strip_config {
+            Some(strip_config) => strip_config.clone(),
+            None => {
+                let segments = encoder_count.max(1) as u32;
+                self.config.resized(width / segments, height)
+            }
+        };
+        let theme = self.theme;
+        let rendered = strip.clone();
+        let image = tokio::task::spawn_blocking(move || {
+            render_strip(&rendered, (width, height), encoder_count, &strip_config, &theme)
+                .map_err(|e| Error::RenderError(e.to_string()))
+        })
+        .await??;
+        self.deck.set_lcd_image(0, 0, image).await?;
+        *self.uploaded_strip.lock().unwrap() = Some(strip);
+        Ok(())
+    }
+
     /// Handle a button press.
     ///
-    /// This method is called when a button is pressed. It updates
+    /// This method is called when a button is pressed. It calls the
+    /// on_chord method of the current view if the press completes a chord,
+    /// clicks the button right away if it repeats while held, and updates
     /// the button state to pressed.
     pub async fn on_press(&self, button: u8) -> Result<(), Box<dyn std::error::Error>> {
-        let view = self.view.read().await;
-        let mut button_matrix = view.render().await?;
-        let button_index = button as usize;
-        let button = button_matrix
-            .get_button_by_index(button_index)
-            .ok_or("Button not found")?;
-        let new_button = button.updated_state(ButtonState::Pressed);
-        button_matrix.set_button_by_index(button_index, new_button)?;
-        self.render_matrix(&button_matrix).await?;
-        Ok(())
+        {
+            let view = self.view.read().await;
+            let repeats = view.repeats(button).await;
+            self.presses.lock().unwrap().insert(
+                button,
+                KeyPress {
+                    pressed_at: Instant::now(),
+                    generation: self.generation.load(Ordering::SeqCst),
+                    next_repeat: repeats.then(|| Instant::now() + self.input.repeat_delay),
+                    in_chord: false,
+                },
+            );
+            let chord = self.complete_chord(view.as_ref(), button).await;
+            if let Some(chord) = chord {
+                let result = view
+                    .on_chord(&self.context, &chord, self.navigation_sender.clone())
+                    .await;
+                if let Err(e) = result {
+                    eprintln!("Error handling chord: {}", e);
+                }
+            } else if repeats {
+                let result = view
+                    .on_click(&self.context, button, self.navigation_sender.clone())
+                    .await;
+                if let Err(e) = result {
+                    eprintln!("Error handling button click: {}", e);
+                }
+            }
+        }
+        self.render().await?;
+        self.render_marquees().await
+    }
+
+    /// Find the chord completed by pressing the given button.
+    ///
+    /// If all buttons of a chord that contains the given button are held,
+    /// and none of them is part of another chord yet, they are marked as
+    /// part of the chord and the chord is returned.
+    async fn complete_chord(&self, view: &dyn View<W, H, C, N>, button: u8) -> Option<Vec<u8>> {
+        let chords = view.chords().await;
+        let generation = self.generation.load(Ordering::SeqCst);
+        let mut presses = self.presses.lock().unwrap();
+        let chord = chords.into_iter().find(|chord| {
+            chord.contains(&button)
+                && chord.iter().all(|key| {
+                    presses
+                        .get(key)
+                        .is_some_and(|press| press.generation == generation && !press.in_chord)
+                })
+        })?;
+        for key in &chord {
+            if let Some(press) = presses.get_mut(key) {
+                press.in_chord = true;
+                press.next_repeat = None;
+            }
+        }
+        self.taps
+            .lock()
+            .unwrap()
+            .retain(|key, _| !chord.contains(key));
+        Some(chord)
     }
 
     /// Handle a button release.
     ///
-    /// This method is called when a button is released. It calls
-    /// the on_click method of the current view.
+    /// This method is called when a button is released. Only a press and
+    /// a release on the same view count as a click, so releasing a button
+    /// that was pressed on a previous view does nothing. Releasing a
+    /// button that repeats while held or that is part of a chord does
+    /// nothing either. Otherwise, it calls the on_long_press method of
+    /// the current view if the button was held for at least the long
+    /// press threshold. Otherwise, the tap is
+    /// delivered to on_click right away, unless the view handles
+    /// multi-tap gestures on the button, in which case it is held back
+    /// until the multi-tap window passes or the longest gesture is
+    /// complete.
     pub async fn on_release(&self, button: u8) -> Result<(), Box<dyn std::error::Error>> {
-        let view = self.view.read().await;
-        let result = view
-            .on_click(&self.context, button, self.navigation_sender.clone())
-            .await;
-        if let Err(e) = result {
-            eprintln!("Error handling button click: {}", e);
+        let press = self.presses.lock().unwrap().remove(&button);
+        let Some(press) = press else {
+            return self.render().await;
+        };
+        if press.generation != self.generation.load(Ordering::SeqCst)
+            || press.in_chord
+            || press.next_repeat.is_some()
+        {
+            return self.render().await;
+        }
+        let long_press = press.pressed_at.elapsed() >= self.input.long_press;
+        {
+            let view = self.view.read().await;
+            let navigation = self.navigation_sender.clone();
+            if long_press {
+                let pending = self.taps.lock().unwrap().remove(&button);
+                if let Some(pending) = pending {
+                    self.deliver_taps(view.as_ref(), button, pending.count).await;
+                }
+                let result = view.on_long_press(&self.context, button, navigation).await;
+                if let Err(e) = result {
+                    eprintln!("Error handling button click: {}", e);
+                }
+            } else {
+                match view.multi_tap_support(button).await {
+                    Some(max) => {
+                        let complete = {
+                            let mut taps = self.taps.lock().unwrap();
+                            let pending = taps.entry(button).or_insert(PendingTaps {
+                                count: 0,
+                                deadline: Instant::now(),
+                            });
+                            pending.count += 1;
+                            pending.deadline = Instant::now() + self.input.multi_tap_window;
+                            if pending.count >= max.count() {
+                                taps.remove(&button).map(|pending| pending.count)
+                            } else {
+                                None
+                            }
+                        };
+                        if let Some(count) = complete {
+                            self.deliver_taps(view.as_ref(), button, count).await;
+                        }
+                    }
+                    None => {
+                        let result = view.on_click(&self.context, button, navigation).await;
+                        if let Err(e) = result {
+                            eprintln!("Error handling button click: {}", e);
+                        }
+                    }
+                }
+            }
         }
         self.render().await?;
         Ok(())
     }
+
+    /// Handle an encoder twist.
+    ///
+    /// This method is called when an encoder is turned. It calls the
+    /// on_encoder_twist method of the current view.
+    pub async fn on_encoder_twist(
+        &self,
+        encoder: u8,
+        ticks: i8,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        {
+            let view = self.view.read().await;
+            let result = view
+                .on_encoder_twist(&self.context, encoder, ticks, self.navigation_sender.clone())
+                .await;
+            if let Err(e) = result {
+                eprintln!("Error handling encoder twist: {}", e);
+            }
+        }
+        self.render().await?;
+        Ok(())
+    }
+
+    /// Handle an encoder press.
+    ///
+    /// This method is called when an encoder is pushed down. It calls the
+    /// on_encoder_press method of the current view.
+    pub async fn on_encoder_press(&self, encoder: u8) -> Result<(), Box<dyn std::error::Error>> {
+        {
+            let view = self.view.read().await;
+            let result = view
+                .on_encoder_press(&self.context, encoder, self.navigation_sender.clone())
+                .await;
+            if let Err(e) = result {
+                eprintln!("Error handling encoder press: {}", e);
+            }
+        }
+        self.render().await?;
+        Ok(())
+    }
+
+    /// Handle an encoder release.
+    ///
+    /// This method is called when a pushed encoder is released. It calls
+    /// the on_encoder_release method of the current view.
+    pub async fn on_encoder_release(&self, encoder: u8) -> Result<(), Box<dyn std::error::Error>> {
+        {
+            let view = self.view.read().await;
+            let result = view
+                .on_encoder_release(&self.context, encoder, self.navigation_sender.clone())
+                .await;
+            if let Err(e) = result {
+                eprintln!("Error handling encoder release: {}", e);
+            }
+        }
+        self.render().await?;
+        Ok(())
+    }
+
+    /// Handle a touch strip gesture.
+    ///
+    /// This method is called when the touch strip is touched or swiped.
+    /// It calls the on_touch method of the current view.
+    pub async fn on_touch(&self, event: TouchEvent) -> Result<(), Box<dyn std::error::Error>> {
+        {
+            let view = self.view.read().await;
+            let result = view
+                .on_touch(&self.context, event, self.navigation_sender.clone())
+                .await;
+            if let Err(e) = result {
+                eprintln!("Error handling touch: {}", e);
+            }
+        }
+        self.render().await?;
+        Ok(())
+    }
+
+    /// Get the next time `on_tick` has work to do.
+    ///
+    /// This method returns `None` if nothing is waiting for a timer.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        let taps = self
+            .taps
+            .lock()
+            .unwrap()
+            .values()
+            .map(|pending| pending.deadline)
+            .min();
+        let repeats = self
+            .presses
+            .lock()
+            .unwrap()
+            .values()
+            .filter_map(|press| press.next_repeat)
+            .min();
+        let marquees = self
+            .marquees
+            .lock()
+            .unwrap()
+            .values()
+            .map(|state| state.next_frame)
+            .min();
+        taps.into_iter().chain(repeats).chain(marquees).min()
+    }
+
+    /// Handle expired timers.
+    ///
+    /// This method delivers the taps whose multi-tap window has passed,
+    /// clicks the held buttons that are due to repeat, and re-renders only
+    /// the keys whose scrolling labels are due to move. It should be
+    /// called once the deadline returned by `next_deadline` is reached.
+    pub async fn on_tick(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let now = Instant::now();
+        let repeated: Vec<u8> = {
+            let mut presses = self.presses.lock().unwrap();
+            presses
+                .iter_mut()
+                .filter_map(|(button, press)| {
+                    let next_repeat = press.next_repeat.as_mut()?;
+                    if *next_repeat > now {
+                        return None;
+                    }
+                    *next_repeat = now + self.input.repeat_interval;
+                    Some(*button)
+                })
+                .collect()
+        };
+        let expired: Vec<(u8, u8)> = {
+            let mut taps = self.taps.lock().unwrap();
+            let expired: Vec<(u8, u8)> = taps
+                .iter()
+                .filter(|(_, pending)| pending.deadline <= now)
+                .map(|(button, pending)| (*button, pending.count))
+                .collect();
+            for (button, _) in &expired {
+                taps.remove(button);
+            }
+            expired
+        };
+        if expired.is_empty() && repeated.is_empty() {
+            return self.render_marquees().await;
+        }
+        {
+            let view = self.view.read().await;
+            for (button, count) in expired {
+                self.deliver_taps(view.as_ref(), button, count).await;
+            }
+            for button in repeated {
+                let result = view
+                    .on_click(&self.context, button, self.navigation_sender.clone())
+                    .await;
+                if let Err(e) = result {
+                    eprintln!("Error handling button click: {}", e);
+                }
+            }
+        }
+        self.render().await?;
+        self.render_marquees().await
+    }
+
+    /// Deliver a number of taps on a button to the view.
+    async fn deliver_taps(&self, view: &dyn View<W, H, C, N>, button: u8, count: u8) {
+        let navigation = self.navigation_sender.clone();
+        let result = match MultiTap::from_count(count) {
+            Some(tap) => view.on_multi_tap(&self.context, button, tap, navigation).await,
+            None => view.on_click(&self.context, button, navigation).await,
+        };
+        if let Err(e) = result {
+            eprintln!("Error handling button click: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        button::Icon,
+        device::{DeckReader, VirtualDeck},
+        view::customizable::{ClickButton, CustomizableView},
+    };
+    use generic_array::typenum::{U3, U5};
+
+    /// The actions performed by the test views, in order.
+    type Log = Arc<Mutex<Vec<String>>>;
+
+    type Manager = DisplayManager<Screen, U5, U3, Log>;
+
+    /// Record an action.
+    fn push(log: &Log, event: &str) {
+        log.lock().unwrap().push(event.to_string());
+    }
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    enum Screen {
+        #[default]
+        Home,
+        Other,
+        Ticker,
+    }
+
+    impl NavigationEntry<U5, U3, Log> for Screen {
+        async fn get_view(
+            &self,
+            _context: Log,
+        ) -> Result<Box<dyn View<U5, U3, Log, Screen>>, Box<dyn std::error::Error>> {
+            let mut view = CustomizableView::new();
+            match self {
+                Screen::Home => {
+                    view.set_button(
+                        0,
+                        0,
+                        ClickButton::new("Hold", None::<Icon>, |log: Log| async move {
+                            push(&log, "click 0");
+                            Ok(())
+                        })
+                        .on_long_press(|log: Log| async move {
+                            push(&log, "long 0");
+                            Ok(())
+                        }),
+                    )?;
+                    view.set_button(
+                        1,
+                        0,
+                        ClickButton::new("Plain", None::<Icon>, |log: Log| async move {
+                            push(&log, "click 1");
+                            Ok(())
+                        }),
+                    )?;
+                    view.set_button(
+                        2,
+                        0,
+                        ClickButton::new("Taps", None::<Icon>, |log: Log| async move {
+                            push(&log, "click 2");
+                            Ok(())
+                        })
+                        .on_double_tap(|log: Log| async move {
+                            push(&log, "double 2");
+                            Ok(())
+                        })
+                        .on_triple_tap(|log: Log| async move {
+                            push(&log, "triple 2");
+                            Ok(())
+                        }),
+                    )?;
+                    view.set_button(
+                        3,
+                        0,
+                        ClickButton::new("Double", None::<Icon>, |log: Log| async move {
+                            push(&log, "click 3");
+                            Ok(())
+                        })
+                        .on_double_tap(|log: Log| async move {
+                            push(&log, "double 3");
+                            Ok(())
+                        }),
+                    )?;
+                    view.set_button(
+                        4,
+                        0,
+                        ClickButton::new("Repeat", None::<Icon>, |log: Log| async move {
+                            push(&log, "click 4");
+                            Ok(())
+                        })
+                        .repeating(),
+                    )?;
+                    for x in 0..2 {
+                        view.set_button(
+                            x,
+                            1,
+                            ClickButton::new("Chord", None::<Icon>, move |log: Log| async move {
+                                push(&log, &format!("click {}", 5 + x));
+                                Ok(())
+                            }),
+                        )?;
+                    }
+                    view.add_chord(&[5, 6], |log: Log| async move {
+                        push(&log, "chord");
+                        Ok(())
+                    })?;
+                }
+                Screen::Other => {
+                    view.set_button(
+                        0,
+                        0,
+                        ClickButton::new("Other", None::<Icon>, |log: Log| async move {
+                            push(&log, "other 0");
+                            Ok(())
+                        }),
+                    )?;
+                }
+                Screen::Ticker => {
+                    view.set_navigation_button(
+                        0,
+                        0,
+                        Screen::Home,
+                        Button::text("Now playing: a rather long track title".to_string())
+                            .with_marquee(200.0),
+                    )?;
+                    view.set_navigation_button(
+                        1,
+                        0,
+                        Screen::Home,
+                        Button::text("Short".to_string()).with_marquee(200.0),
+                    )?;
+                }
+            }
+            Ok(Box::new(view))
+        }
+    }
+
+    /// A virtual Stream Deck that records which keys are uploaded.
+    struct CountingDeck {
+        inner: VirtualDeck,
+        uploads: Mutex<Vec<u8>>,
+    }
+
+    impl CountingDeck {
+        fn new() -> Self {
+            CountingDeck {
+                inner: VirtualDeck::new(15),
+                uploads: Mutex::new(Vec::new()),
+            }
+        }
+
+        /// Take the indices of the keys uploaded so far, in order.
+        fn take_uploads(&self) -> Vec<u8> {
+            std::mem::take(&mut *self.uploads.lock().unwrap())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl DeckDevice for CountingDeck {
+        async fn set_button_image(
+            &self,
+            index: u8,
+            image: DynamicImage,
+        ) -> crate::error::Result<()> {
+            self.uploads.lock().unwrap().push(index);
+            self.inner.set_button_image(index, image).await
+        }
+
+        async fn flush(&self) -> crate::error::Result<()> {
+            self.inner.flush().await
+        }
+
+        async fn set_brightness(&self, percent: u8) -> crate::error::Result<()> {
+            self.inner.set_brightness(percent).await
+        }
+
+        fn reader(&self) -> Arc<dyn DeckReader> {
+            self.inner.reader()
+        }
+    }
+
+    /// Start a display manager on a virtual Stream Deck.
+    async fn manager(input: InputConfig) -> (Manager, Log) {
+        manager_on(Arc::new(VirtualDeck::new(15)), input).await
+    }
+
+    /// Start a display manager on the given device.
+    async fn manager_on(deck: Arc<dyn DeckDevice>, input: InputConfig) -> (Manager, Log) {
+        let log = Log::default();
+        let (manager, _) =
+            DisplayManager::new(deck, RenderConfig::default(), Theme::default(), log.clone())
+                .await
+                .unwrap();
+        let manager = manager.with_input_config(input);
+        manager.render().await.unwrap();
+        (manager, log)
+    }
+
+    /// Wait for the given duration, handling timers like the run loop does.
+    async fn wait(manager: &Manager, duration: Duration) {
+        let end = Instant::now() + duration;
+        while let Some(deadline) = manager.next_deadline().filter(|deadline| *deadline <= end) {
+            tokio::time::sleep_until(deadline.into()).await;
+            manager.on_tick().await.unwrap();
+        }
+        tokio::time::sleep_until(end.into()).await;
+    }
+
+    /// Take the actions recorded so far.
+    fn take(log: &Log) -> Vec<String> {
+        std::mem::take(&mut *log.lock().unwrap())
+    }
+
+    #[tokio::test]
+    async fn long_press_or_click_at_threshold() {
+        let threshold = Duration::from_millis(200);
+        let (manager, log) = manager(InputConfig::new().with_long_press(threshold)).await;
+
+        manager.on_press(0).await.unwrap();
+        manager.on_release(0).await.unwrap();
+        assert_eq!(take(&log), ["click 0"]);
+
+        manager.on_press(0).await.unwrap();
+        wait(&manager, threshold - Duration::from_millis(120)).await;
+        manager.on_release(0).await.unwrap();
+        assert_eq!(take(&log), ["click 0"]);
+
+        manager.on_press(0).await.unwrap();
+        wait(&manager, threshold).await;
+        assert!(take(&log).is_empty());
+        manager.on_release(0).await.unwrap();
+        assert_eq!(take(&log), ["long 0"]);
+    }
+
+    #[tokio::test]
+    async fn long_press_without_action_clicks() {
+        let threshold = Duration::from_millis(100);
+        let (manager, log) = manager(InputConfig::new().with_long_press(threshold)).await;
+
+        manager.on_press(1).await.unwrap();
+        wait(&manager, threshold).await;
+        manager.on_release(1).await.unwrap();
+        assert_eq!(take(&log), ["click 1"]);
+    }
+
+    /// Press and release a key.
+    async fn click(manager: &Manager, button: u8) {
+        manager.on_press(button).await.unwrap();
+        manager.on_release(button).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn taps_wait_for_multi_tap_window() {
+        let window = Duration::from_millis(300);
+        let (manager, log) = manager(InputConfig::new().with_multi_tap_window(window)).await;
+
+        click(&manager, 2).await;
+        assert!(take(&log).is_empty());
+        assert!(manager.next_deadline().is_some());
+        wait(&manager, window + Duration::from_millis(50)).await;
+        assert_eq!(take(&log), ["click 2"]);
+
+        click(&manager, 2).await;
+        click(&manager, 2).await;
+        assert!(take(&log).is_empty());
+        wait(&manager, window + Duration::from_millis(50)).await;
+        assert_eq!(take(&log), ["double 2"]);
+        assert!(manager.next_deadline().is_none());
+    }
+
+    #[tokio::test]
+    async fn longest_tap_gesture_is_delivered_at_once() {
+        let window = Duration::from_millis(300);
+        let (manager, log) = manager(InputConfig::new().with_multi_tap_window(window)).await;
+
+        click(&manager, 2).await;
+        click(&manager, 2).await;
+        click(&manager, 2).await;
+        assert_eq!(take(&log), ["triple 2"]);
+
+        click(&manager, 3).await;
+        assert!(take(&log).is_empty());
+        click(&manager, 3).await;
+        assert_eq!(take(&log), ["double 3"]);
+        assert!(manager.next_deadline().is_none());
+    }
+
+    #[tokio::test]
+    async fn taps_on_other_keys_do_not_wait() {
+        let (manager, log) = manager(InputConfig::new()).await;
+
+        click(&manager, 1).await;
+        assert_eq!(take(&log), ["click 1"]);
+        assert!(manager.next_deadline().is_none());
+    }
+
+    #[tokio::test]
+    async fn held_key_repeats_without_click_on_release() {
+        let delay = Duration::from_millis(150);
+        let interval = Duration::from_millis(50);
+        let input = InputConfig::new()
+            .with_repeat_delay(delay)
+            .with_repeat_interval(interval)
+            .with_long_press(Duration::from_millis(100));
+        let (manager, log) = manager(input).await;
+
+        manager.on_press(4).await.unwrap();
+        assert_eq!(take(&log), ["click 4"]);
+        wait(&manager, delay - Duration::from_millis(50)).await;
+        assert!(take(&log).is_empty());
+        wait(&manager, Duration::from_millis(50) + interval * 2 + interval / 2).await;
+        let repeats = take(&log);
+        assert!(repeats.len() >= 2, "{:?}", repeats);
+        assert!(repeats.iter().all(|event| event == "click 4"), "{:?}", repeats);
+
+        manager.on_release(4).await.unwrap();
+        assert!(take(&log).is_empty());
+        assert!(manager.next_deadline().is_none());
+    }
+
+    #[tokio::test]
+    async fn chord_does_not_click_its_keys() {
+        let (manager, log) = manager(InputConfig::new()).await;
+
+        manager.on_press(5).await.unwrap();
+        manager.on_press(6).await.unwrap();
+        assert_eq!(take(&log), ["chord"]);
+        manager.on_release(6).await.unwrap();
+        manager.on_release(5).await.unwrap();
+        assert!(take(&log).is_empty());
+
+        click(&manager, 5).await;
+        assert_eq!(take(&log), ["click 5"]);
+    }
+
+    #[tokio::test]
+    async fn release_after_navigation_does_not_click() {
+        let (manager, log) = manager(InputConfig::new()).await;
+
+        manager.on_press(0).await.unwrap();
+        manager.navigate_to(Screen::Other).await.unwrap();
+        manager.on_release(0).await.unwrap();
+        assert!(take(&log).is_empty());
+
+        click(&manager, 0).await;
+        assert_eq!(take(&log), ["other 0"]);
+    }
+
+    #[tokio::test]
+    async fn screenshot_shows_held_keys_as_pressed() {
+        let (manager, _) = manager(InputConfig::new()).await;
+        let layout = ScreenshotLayout::default();
+        let idle = manager.screenshot(&layout).await.unwrap();
+
+        manager.on_press(1).await.unwrap();
+        let held = manager.screenshot(&layout).await.unwrap();
+        let pressed = Theme::default().pressed_background;
+        assert_eq!(
+            held.to_rgba8().get_pixel(24 + 72 + 16, 24).0,
+            [
+                (pressed.red() * 255.0) as u8,
+                (pressed.green() * 255.0) as u8,
+                (pressed.blue() * 255.0) as u8,
+                255,
+            ]
+        );
+
+        manager.on_release(1).await.unwrap();
+        assert!(manager.screenshot(&layout).await.unwrap() == idle);
+    }
+
+    #[tokio::test]
+    async fn only_changed_keys_are_uploaded() {
+        let deck = Arc::new(CountingDeck::new());
+        let (manager, _) = manager_on(deck.clone(), InputConfig::default()).await;
+        assert_eq!(deck.take_uploads().len(), 15);
+        assert_eq!(deck.inner.flush_count(), 1);
+
+        manager.render().await.unwrap();
+        assert!(deck.take_uploads().is_empty());
+        assert_eq!(deck.inner.flush_count(), 1);
+
+        manager.on_press(1).await.unwrap();
+        assert_eq!(deck.take_uploads(), [1]);
+        assert_eq!(deck.inner.flush_count(), 2);
+        manager.on_release(1).await.unwrap();
+        assert_eq!(deck.take_uploads(), [1]);
+        assert_eq!(deck.inner.flush_count(), 3);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn parallel_renders_reach_their_keys() {
+        let deck = Arc::new(VirtualDeck::new(15));
+        let (manager, _) = manager_on(deck.clone(), InputConfig::default()).await;
+
+        let config = RenderConfig::default();
+        let theme = Theme::default();
+        let matrix = manager.current_matrix().await.unwrap();
+        for index in 0..15 {
+            let button = matrix.get_button_by_index(index).unwrap();
+            let expected = render_button(
+                &button.to_render_button(&theme),
+                &button.render_config(&config),
+            )
+            .unwrap();
+            assert!(
+                deck.key_image(index as u8).as_ref() == Some(&expected),
+                "key {} shows another image",
+                index
+            );
+        }
+        assert!(deck.key_image(0) != deck.key_image(1));
+    }
+
+    #[tokio::test]
+    async fn only_scrolling_labels_are_re_rendered_on_tick() {
+        let deck = Arc::new(CountingDeck::new());
+        let (manager, _) = manager_on(deck.clone(), InputConfig::default()).await;
+        assert!(manager.next_deadline().is_none());
+        manager.navigate_to(Screen::Ticker).await.unwrap();
+        manager.render().await.unwrap();
+        deck.take_uploads();
+        let start = deck.inner.key_image(0).unwrap();
+        let short = deck.inner.key_image(1).unwrap();
+
+        let deadline = manager.next_deadline().expect("the label does not scroll");
+        tokio::time::sleep_until((deadline + Duration::from_millis(50)).into()).await;
+        manager.on_tick().await.unwrap();
+        assert_eq!(deck.take_uploads(), [0]);
+        assert!(deck.inner.key_image(0).unwrap() != start);
+        assert!(deck.inner.key_image(1).unwrap() == short);
+
+        manager.navigate_to(Screen::Home).await.unwrap();
+        manager.render().await.unwrap();
+        assert!(manager.next_deadline().is_none());
+    }
 }
@@ -6,18 +6,23 @@
 mod button;
 mod matrix;
 mod manager;
+mod strip;
 pub mod customizable;
 
 // Re-export public items
 pub use self::button::{Button, ButtonState};
 pub use self::matrix::ButtonMatrix;
 pub use self::manager::DisplayManager;
+pub use self::strip::Strip;
 
 use std::sync::Arc;
 use tokio::sync::mpsc;
 use generic_array::ArrayLength;
 
-use crate::navigation::NavigationEntry;
+use crate::{
+    input::{MultiTap, TouchEvent},
+    navigation::NavigationEntry,
+};
 
 /// A trait for views in a Stream Deck application.
 ///
@@ -53,6 +58,142 @@ where
         navigation: Arc<mpsc::Sender<N>>,
     ) -> Result<(), Box<dyn std::error::Error>>;
 
+    /// Handle a long press.
+    ///
+    /// This method is called when a button is released after being held
+    /// for at least the long press threshold. The default implementation
+    /// treats it as a click.
+    async fn on_long_press(
+        &self,
+        context: &C,
+        index: u8,
+        navigation: Arc<mpsc::Sender<N>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.on_click(context, index, navigation).await
+    }
+
+    /// Get the longest multi-tap gesture handled by a button.
+    ///
+    /// Clicks on buttons that handle multi-tap gestures are delayed until
+    /// it is clear that no further tap follows. The default implementation
+    /// returns `None`, so clicks are delivered immediately.
+    async fn multi_tap_support(&self, _index: u8) -> Option<MultiTap> {
+        None
+    }
+
+    /// Check whether the button at the given index repeats while held.
+    ///
+    /// Repeating buttons are clicked as soon as they are pressed, and then
+    /// again at a fixed rate until they are released. Releasing them does
+    /// not click them. The default implementation returns `false`.
+    async fn repeats(&self, _index: u8) -> bool {
+        false
+    }
+
+    /// Handle a multi-tap gesture.
+    ///
+    /// This method is called for buttons that handle multi-tap gestures
+    /// when a button is tapped several times in quick succession.
+    /// The default implementation treats every tap as a click.
+    async fn on_multi_tap(
+        &self,
+        context: &C,
+        index: u8,
+        tap: MultiTap,
+        navigation: Arc<mpsc::Sender<N>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        for _ in 0..tap.count() {
+            self.on_click(context, index, navigation.clone()).await?;
+        }
+        Ok(())
+    }
+
+    /// Get the key combinations handled by the view.
+    ///
+    /// Each chord is a list of button indices. When all buttons of a chord
+    /// are held at once, on_chord is called, and releasing those buttons
+    /// does not click them. The default implementation returns no chords.
+    async fn chords(&self) -> Vec<Vec<u8>> {
+        Vec::new()
+    }
+
+    /// Handle a chord.
+    ///
+    /// This method is called when all buttons of one of the chords
+    /// returned by `chords` are held at once. The keys are given in the
+    /// same order as in the chord. The default implementation does nothing.
+    async fn on_chord(
+        &self,
+        _context: &C,
+        _keys: &[u8],
+        _navigation: Arc<mpsc::Sender<N>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    /// Handle an encoder twist.
+    ///
+    /// This method is called when an encoder, such as a dial on the
+    /// Stream Deck+, is turned. Positive ticks are clockwise, negative
+    /// ticks are counter-clockwise. The default implementation does nothing.
+    async fn on_encoder_twist(
+        &self,
+        _context: &C,
+        _encoder: u8,
+        _ticks: i8,
+        _navigation: Arc<mpsc::Sender<N>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    /// Handle an encoder press.
+    ///
+    /// This method is called when an encoder is pushed down.
+    /// The default implementation does nothing.
+    async fn on_encoder_press(
+        &self,
+        _context: &C,
+        _encoder: u8,
+        _navigation: Arc<mpsc::Sender<N>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    /// Handle an encoder release.
+    ///
+    /// This method is called when a pushed encoder is released.
+    /// The default implementation does nothing.
+    async fn on_encoder_release(
+        &self,
+        _context: &C,
+        _encoder: u8,
+        _navigation: Arc<mpsc::Sender<N>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    /// Render the touch strip.
+    ///
+    /// This method is called next to `render` on devices with a touch
+    /// strip. The default implementation returns `None`, which leaves the
+    /// strip unchanged.
+    async fn render_strip(&self) -> Result<Option<Strip>, Box<dyn std::error::Error>> {
+        Ok(None)
+    }
+
+    /// Handle a touch strip gesture.
+    ///
+    /// This method is called when the touch strip is touched or swiped.
+    /// The default implementation does nothing.
+    async fn on_touch(
+        &self,
+        _context: &C,
+        _event: TouchEvent,
+        _navigation: Arc<mpsc::Sender<N>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
     /// Fetch state for all buttons in the view.
     ///
     /// This method is called to fetch the state for all buttons in the view.
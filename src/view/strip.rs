@@ -0,0 +1,149 @@
+//! Touch strip content for the view system.
+//!
+//! This module provides a type for describing what a view shows on the
+//! touch strip of devices like the Stream Deck+.
+
+use image::{imageops, DynamicImage, Rgba, RgbaImage};
+
+use crate::{
+    button::{render_button, RenderConfig},
+    theme::Theme,
+};
+
+use super::button::Button;
+
+/// The content of the touch strip.
+#[derive(Clone, PartialEq)]
+pub enum Strip {
+    /// One button per segment, each drawn above the encoder with the
+    /// same index.
+    Segments(Vec<Button>),
+    /// One image covering the whole strip.
+    ///
+    /// Images of a different size are scaled to fit the strip, keeping
+    /// their aspect ratio, and centered on a black background.
+    Image(DynamicImage),
+}
+
+/// Render the touch strip to a single image.
+///
+/// Segments are rendered like keys, with the given segment config, and
+/// centered in equal parts of the strip. The strip is divided into one
+/// part per encoder, or one part per segment on devices without encoders.
+pub(crate) fn render_strip(
+    strip: &Strip,
+    size: (u32, u32),
+    encoder_count: u8,
+    config: &RenderConfig,
+    theme: &Theme,
+) -> Result<DynamicImage, Box<dyn std::error::Error>> {
+    let (width, height) = size;
+    match strip {
+        Strip::Image(image) => {
+            if image.width() == width && image.height() == height {
+                return Ok(image.clone());
+            }
+            let scaled = image.resize(width, height, imageops::FilterType::Triangle);
+            let mut canvas = RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, 255]));
+            imageops::overlay(
+                &mut canvas,
+                &scaled.to_rgba8(),
+                (width as i64 - scaled.width() as i64) / 2,
+                (height as i64 - scaled.height() as i64) / 2,
+            );
+            Ok(DynamicImage::ImageRgba8(canvas))
+        }
+        Strip::Segments(segments) => {
+            let mut image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+                width,
+                height,
+                Rgba([0, 0, 0, 255]),
+            ));
+            let count = if encoder_count > 0 {
+                encoder_count as u32
+            } else {
+                segments.len().max(1) as u32
+            };
+            let segment_width = width / count;
+            for (index, segment) in segments.iter().take(count as usize).enumerate() {
+                let rendered = render_button(
+                    &segment.to_render_button(theme),
+                    &segment.render_config(config),
+                )?;
+                let x = index as u32 * segment_width
+                    + segment_width.saturating_sub(rendered.width()) / 2;
+                let y = height.saturating_sub(rendered.height()) / 2;
+                imageops::replace(&mut image, &rendered, x as i64, y as i64);
+            }
+            Ok(image)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::GenericImageView;
+
+    /// An icon that fills its whole view box.
+    const SQUARE: &str =
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 24 24"><rect width="24" height="24"/></svg>"#;
+
+    /// Get the runs of rows of a strip segment that have foreground pixels.
+    fn ink_rows(image: &DynamicImage, columns: std::ops::Range<u32>) -> Vec<(u32, u32)> {
+        let mut runs: Vec<(u32, u32)> = Vec::new();
+        for y in 0..image.height() {
+            let ink = columns.clone().any(|x| image.get_pixel(x, y)[0] > 128);
+            match runs.last_mut() {
+                Some((_, end)) if ink && *end == y => *end = y + 1,
+                _ if ink => runs.push((y, y + 1)),
+                _ => {}
+            }
+        }
+        runs
+    }
+
+    #[test]
+    fn icon_fits_wide_segment() {
+        let (width, height) = (800, 100);
+        let config = RenderConfig::default().resized(width / 4, height);
+        let segments = (0..4)
+            .map(|_| Button::with_icon("Label".to_string(), SQUARE))
+            .collect();
+        let image =
+            render_strip(&Strip::Segments(segments), (width, height), 4, &config, &Theme::default())
+                .unwrap();
+
+        let runs = ink_rows(&image, 0..width / 4);
+        assert!(runs.len() >= 2, "icon and label overlap: {:?}", runs);
+        let (icon_top, icon_bottom) = runs[0];
+        assert_eq!(icon_bottom - icon_top, 60, "{:?}", runs);
+        assert_eq!(icon_top, 15, "{:?}", runs);
+        let icon_columns: Vec<u32> = (0..width / 4)
+            .filter(|x| image.get_pixel(*x, icon_top + 1)[0] > 128)
+            .collect();
+        assert_eq!(icon_columns.len(), 60);
+        assert_eq!(icon_columns[0], 70);
+    }
+
+    #[test]
+    fn image_keeps_aspect_ratio() {
+        let square = DynamicImage::ImageRgba8(RgbaImage::from_pixel(50, 50, Rgba([255; 4])));
+        let image = render_strip(
+            &Strip::Image(square),
+            (800, 100),
+            4,
+            &RenderConfig::default(),
+            &Theme::default(),
+        )
+        .unwrap();
+
+        let white: Vec<u32> = (0..800)
+            .filter(|x| image.get_pixel(*x, 50)[0] > 128)
+            .collect();
+        assert_eq!(white.len(), 100);
+        assert_eq!(white[0], 350);
+        assert_eq!(image.get_pixel(400, 0)[0], 255);
+        assert_eq!(image.get_pixel(0, 50), Rgba([0, 0, 0, 255]));
+    }
+}